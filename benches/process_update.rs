@@ -1,5 +1,5 @@
 use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
-use orderbook::{OrderBook, TickLevel, TickUpdate, old_book::BTreeOrderBook};
+use orderbook::{BoxedOrderBook, OrderBook, TickLevel, TickUpdate, old_book::BTreeOrderBook};
 
 fn tl(tick: u32, size: f64) -> TickLevel {
     TickLevel { tick, size }
@@ -18,6 +18,7 @@ fn create_tick_update(side_size: usize, midprice: u32) -> TickUpdate {
 
     TickUpdate {
         sequence_id: 0,
+        timestamp: None,
         asks: ask_levels,
         bids: bid_levels,
     }
@@ -32,12 +33,12 @@ fn bench_process_tick_update(c: &mut Criterion) {
             group.bench_function($name, move |b| {
                 let mut book = $constructor;
                 let update = create_tick_update(20, MIDPRICE_TICK);
-                book.process_tick_update(&update);
+                let _ = book.process_tick_update(&update);
 
                 b.iter_batched(
                     || book.clone(),
                     |mut book| {
-                        book.process_tick_update(black_box(&update));
+                        let _ = book.process_tick_update(black_box(&update));
                     },
                     BatchSize::SmallInput,
                 );
@@ -70,7 +71,7 @@ fn bench_midprice_trend_up(c: &mut Criterion) {
                 let mut book = $constructor;
                 // Initial state
                 let initial_update = create_tick_update(side_size, MIDPRICE_TICK);
-                book.process_tick_update(&initial_update);
+                let _ = book.process_tick_update(&initial_update);
 
                 // Create all updates upfront
                 let mut updates = Vec::with_capacity(iterations * 2);
@@ -84,7 +85,7 @@ fn bench_midprice_trend_up(c: &mut Criterion) {
                     || book.clone(),
                     |mut book| {
                         for update in &updates {
-                            book.process_tick_update(black_box(update));
+                            let _ = book.process_tick_update(black_box(update));
                         }
                     },
                     BatchSize::SmallInput,
@@ -117,7 +118,7 @@ fn bench_midprice_trend_down(c: &mut Criterion) {
             group.bench_function($name, move |b| {
                 let mut book = $constructor;
                 let initial_update = create_tick_update(side_size, MIDPRICE_TICK);
-                book.process_tick_update(&initial_update);
+                let _ = book.process_tick_update(&initial_update);
 
                 // Create all updates upfront
                 let mut updates = Vec::with_capacity(iterations * 2);
@@ -131,7 +132,7 @@ fn bench_midprice_trend_down(c: &mut Criterion) {
                     || book.clone(),
                     |mut book| {
                         for update in &updates {
-                            book.process_tick_update(black_box(update));
+                            let _ = book.process_tick_update(black_box(update));
                         }
                     },
                     BatchSize::SmallInput,
@@ -165,7 +166,7 @@ fn bench_midprice_volatile(c: &mut Criterion) {
                 let mut book = $constructor;
                 // Initial state
                 let initial_update = create_tick_update(side_size, MIDPRICE_TICK);
-                book.process_tick_update(&initial_update);
+                let _ = book.process_tick_update(&initial_update);
 
                 // Create all updates upfront
                 let mut updates = Vec::with_capacity(iterations * 2);
@@ -181,7 +182,7 @@ fn bench_midprice_volatile(c: &mut Criterion) {
                     || book.clone(),
                     |mut book| {
                         for update in &updates {
-                            book.process_tick_update(black_box(update));
+                            let _ = book.process_tick_update(black_box(update));
                         }
                     },
                     BatchSize::SmallInput,
@@ -201,11 +202,144 @@ fn bench_midprice_volatile(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_midprice_stationary(c: &mut Criterion) {
+    let mut group = c.benchmark_group("midprice_stationary");
+    let side_size = 20;
+    let iterations = 10;
+
+    group.bench_function("midprice_stationary slots: 128, empty: 32", move |b| {
+        let mut book = OrderBook::<128, 32>::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&create_tick_update(side_size, MIDPRICE_TICK));
+
+        // steady state: sizes churn on the same ticks, the best never moves
+        let updates: Vec<_> = (0..iterations)
+            .map(|i| {
+                let mut update = create_tick_update(side_size, MIDPRICE_TICK);
+                update.sequence_id = i as u64 + 1;
+                for level in update.asks.iter_mut().chain(update.bids.iter_mut()) {
+                    level.size += 1.0 + i as f64;
+                }
+                update
+            })
+            .collect();
+
+        b.iter_batched(
+            || book.clone(),
+            |mut book| {
+                for update in &updates {
+                    let _ = book.process_tick_update(black_box(update));
+                }
+            },
+            BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+}
+
+fn bench_best_removed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("best_removed");
+    let side_size = 20;
+    let iterations = 10;
+
+    group.bench_function("best_removed slots: 128, empty: 32", move |b| {
+        let mut book = OrderBook::<128, 32>::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&create_tick_update(side_size, MIDPRICE_TICK));
+
+        // peel the top of book off one level at a time, forcing a best rescan
+        let updates: Vec<_> = (0..iterations as u32)
+            .map(|i| TickUpdate {
+                sequence_id: i as u64 + 1,
+                timestamp: None,
+                asks: vec![tl(MIDPRICE_TICK + 1 + i, 0.0)],
+                bids: vec![tl(MIDPRICE_TICK - 1 - i, 0.0)],
+            })
+            .collect();
+
+        b.iter_batched(
+            || book.clone(),
+            |mut book| {
+                for update in &updates {
+                    let _ = book.process_tick_update(black_box(update));
+                }
+            },
+            BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+}
+
+fn bench_snapshot_load(c: &mut Criterion) {
+    let mut group = c.benchmark_group("snapshot_load");
+    let update = create_tick_update(200, MIDPRICE_TICK);
+
+    group.bench_function("incremental slots: 128, empty: 32", |b| {
+        b.iter(|| {
+            let mut book = OrderBook::<128, 32>::new(2u8.try_into().unwrap());
+            let _ = book.process_tick_update(black_box(&update));
+            book
+        });
+    });
+
+    group.bench_function("from_levels slots: 128, empty: 32", |b| {
+        b.iter(|| {
+            OrderBook::<128, 32>::from_levels(
+                2u8.try_into().unwrap(),
+                0,
+                black_box(&update.asks),
+                black_box(&update.bids),
+            )
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_clone(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clone");
+    let update = create_tick_update(20, MIDPRICE_TICK);
+
+    macro_rules! bench_clone {
+        ($name:expr, $constructor:expr) => {
+            group.bench_function($name, |b| {
+                let mut book = $constructor;
+                let _ = book.process_tick_update(&update);
+
+                b.iter(|| black_box(&book).clone());
+            });
+        };
+    }
+
+    bench_clone!(
+        "inline slots: 128, empty: 32",
+        OrderBook::<128, 32>::new(2u8.try_into().unwrap())
+    );
+    bench_clone!(
+        "boxed slots: 128, empty: 32",
+        BoxedOrderBook::<128, 32>::new(2u8.try_into().unwrap())
+    );
+    bench_clone!(
+        "inline slots: 4096, empty: 64",
+        OrderBook::<4096, 64>::new(2u8.try_into().unwrap())
+    );
+    bench_clone!(
+        "boxed slots: 4096, empty: 64",
+        BoxedOrderBook::<4096, 64>::new(2u8.try_into().unwrap())
+    );
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_process_tick_update,
     bench_midprice_trend_up,
     bench_midprice_trend_down,
-    bench_midprice_volatile
+    bench_midprice_volatile,
+    bench_midprice_stationary,
+    bench_best_removed,
+    bench_snapshot_load,
+    bench_clone
 );
 criterion_main!(benches);
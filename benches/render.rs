@@ -0,0 +1,68 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use orderbook::{OrderBook, TickLevel, TickUpdate};
+
+const MIDPRICE_TICK: u32 = u32::MAX / 2;
+
+// dense window, every level read each frame like a redraw would
+fn bench_render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+
+    let mut book = OrderBook::<128, 32>::new(2u8.try_into().unwrap());
+    let _ = book.process_tick_update(&TickUpdate {
+        sequence_id: 0,
+        timestamp: None,
+        asks: (0..90)
+            .map(|i| TickLevel {
+                tick: MIDPRICE_TICK + 1 + i,
+                size: 1.0 + i as f64,
+            })
+            .collect(),
+        bids: (0..90)
+            .map(|i| TickLevel {
+                tick: MIDPRICE_TICK - 1 - i,
+                size: 1.0 + i as f64,
+            })
+            .collect(),
+    });
+
+    group.bench_function("levels slots: 128, empty: 32", |b| {
+        b.iter(|| {
+            let book = black_box(&book);
+            let asks: f64 = book.asks().map(|l| l.price * l.size).sum();
+            let bids: f64 = book.bids().map(|l| l.price * l.size).sum();
+            asks + bids
+        });
+    });
+
+    group.finish();
+}
+
+// top few levels of a side whose heap is tiny vs huge. the heap is only
+// entered once the cache runs out, and only walked as far as it's read
+fn bench_take(c: &mut Criterion) {
+    let mut group = c.benchmark_group("asks_take_5");
+
+    for heap_levels in [16u32, 100_000] {
+        let mut book = OrderBook::<128, 32>::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            // 2 in the cache, the rest past the window
+            asks: [MIDPRICE_TICK + 1, MIDPRICE_TICK + 2]
+                .into_iter()
+                .chain((0..heap_levels).map(|i| MIDPRICE_TICK + 1000 + i))
+                .map(|tick| TickLevel { tick, size: 1.0 })
+                .collect(),
+            bids: vec![],
+        });
+
+        group.bench_function(format!("heap levels: {heap_levels}"), |b| {
+            b.iter(|| black_box(&book).asks().take(5).map(|l| l.size).sum::<f64>());
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_render, bench_take);
+criterion_main!(benches);
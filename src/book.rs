@@ -1,46 +1,282 @@
+mod analytics;
+mod bbo;
+mod coarsen;
+mod consolidate;
+mod events;
+mod frame;
+mod history;
+mod hysteresis;
+mod iter;
+#[cfg(feature = "price-memo")]
+mod price_memo;
+mod snapshot;
+mod snapshot_view;
+mod spread_tracker;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+mod validate;
+mod view;
+
 use std::collections::BTreeMap;
 
 use tabled::{
-    Table,
+    Table, Tabled,
     settings::{Style, panel::Header},
 };
 
-use crate::{FloatLevel, TickLevel, TickUpdate, tick::Decimals};
+use events::{Events, Observer, level_event};
+
+use crate::{
+    FloatLevel, Side, TickLevel, TickUpdate,
+    cache_storage::{CacheStorage, Inline},
+    heap_alloc::{Global, HeapAlloc, new_heap},
+    tick::{DecimalRangeError, Decimals, Tick, TickConversionError},
+};
+
+pub use analytics::HistogramOverflow;
+pub use bbo::BboBook;
+pub use coarsen::CoarseRounding;
+pub use consolidate::ConsolidateError;
+pub use events::{BookEvent, EventSink};
+pub use frame::BookFrame;
+pub use history::HistoryBook;
+pub use hysteresis::HysteresisBook;
+pub use iter::{AskIter, BidIter};
+pub use snapshot::{BookSnapshot, Mismatch};
+pub use snapshot_view::{SnapshotView, SnapshotViewError, view_from_bytes};
+pub use spread_tracker::SpreadTracker;
+#[cfg(any(test, feature = "testing"))]
+pub use testing::{BookState, simulate_update};
+pub use validate::{BookOrderingError, InvariantViolation};
+pub use view::{BookView, books_equivalent};
 
 pub const EPSILON: f64 = 1e-15;
 
+/// whether a stored size is a level rather than an empty slot. anything
+/// below `EPSILON` is stored as 0.0, so the cache never holds negatives
+#[inline]
+pub(crate) fn populated(size: f64) -> bool {
+    size >= EPSILON
+}
+
+/// what happens to levels that fall outside the cache window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeapPolicy {
+    /// spill every level to the heap
+    #[default]
+    Keep,
+    /// spill levels at most `n` ticks from the best, discard the rest.
+    /// the distance is checked when a level enters the heap, levels already
+    /// there are not revisited as the best moves
+    DropBeyond(u32),
+    /// never spill, every level outside the window is discarded. the heaps
+    /// stay empty so updates never allocate
+    Disabled,
+}
+
+/// what `process_tick_update` does with an update carrying the same
+/// `sequence_id` as the last one, e.g. a venue resending a corrected snapshot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EqualSequencePolicy {
+    /// apply it like any other update
+    #[default]
+    Apply,
+    /// drop it, the book is left untouched
+    Ignore,
+    /// clear the book then apply it, for resends that carry the full book
+    Replace,
+}
+
+/// counters describing how the book has been handling its levels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BookStats {
+    /// levels that would have spilled to the heap but were discarded by the
+    /// `HeapPolicy`
+    pub dropped_spills: u64,
+}
+
+/// whether applying an update changed any level of the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use = "`Unchanged` means no level moved, callers can skip downstream work"]
+pub enum UpdateOutcome {
+    /// at least one level's size changed by more than `EPSILON`
+    Changed,
+    /// every level already held the size the update set. the best can only
+    /// move when some size changes, so it didn't move either
+    Unchanged,
+}
+
+/// Error when an update can't be applied to the book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpdateError<T = u32> {
+    /// size is negative or NaN
+    InvalidSize { side: Side, tick: T, size: f64 },
+}
+
+impl<T: Tick> std::fmt::Display for UpdateError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::InvalidSize { side, tick, size } => {
+                write!(f, "invalid size {size} for {side:?} at tick {tick}")
+            }
+        }
+    }
+}
+
+/// rounds `level` to the nearest multiple of the lot `step`, if any
+#[inline(always)]
+fn on_size_step<T: Tick>(level: TickLevel<T>, step: Option<f64>) -> TickLevel<T> {
+    match step {
+        Some(step) => TickLevel {
+            size: (level.size / step).round() * step,
+            ..level
+        },
+        None => level,
+    }
+}
+
+/// dense `from..=to` walk over `entries`, lowest tick first, 0.0 for gaps
+fn grid<T: Tick>(
+    entries: impl Iterator<Item = (T, f64)>,
+    from: T,
+    to: T,
+    decimals: Decimals,
+) -> impl Iterator<Item = FloatLevel> {
+    let len = match to.checked_sub(from) {
+        Some(span) => span.to_index().saturating_add(1),
+        None => 0,
+    };
+    let mut entries = entries.skip_while(move |(tick, _)| *tick < from).peekable();
+
+    (0..len).map(move |i| {
+        let tick = from + T::from_index(i);
+        let size = entries
+            .next_if(|(level, _)| *level == tick)
+            .map_or(0.0, |(_, size)| size);
+        FloatLevel {
+            price: decimals.tick_to_f64(tick),
+            size,
+        }
+    })
+}
+
 #[derive(Debug, Clone)]
-pub struct OrderBook<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize> {
+pub struct OrderBook<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick = u32,
+    A: HeapAlloc = Global,
+    C: CacheStorage<CACHE_SLOTS> = Inline<CACHE_SLOTS>,
+> {
     sequence_id: u64,
+    last_timestamp: Option<u64>,
 
     tick_decimals: Decimals,
 
-    asks_0_tick: u32,
-    bids_0_tick: u32,
+    asks_0_tick: T,
+    bids_0_tick: T,
 
+    // a side is empty when the slot at its best index is unpopulated.
+    // index 0 is the window's anchor tick, usually padding after a rebalance
+    // but a legitimate best once a level lands exactly on it: the padding is
+    // used up and the next better level rebalances
     best_ask_i: u16,
     best_bid_i: u16,
 
     // invariant: tick index is lowest to highest
-    asks: [f64; CACHE_SLOTS],
+    asks: C,
     // invariant: tick index is highest to lowest
-    bids: [f64; CACHE_SLOTS],
-
-    asks_heap: BTreeMap<u32, f64>,
-    bids_heap: BTreeMap<u32, f64>,
+    bids: C,
+
+    // (size, sequence id that last wrote the level), see `evict_stale`
+    #[cfg(not(feature = "allocator_api"))]
+    asks_heap: BTreeMap<T, (f64, u64)>,
+    #[cfg(not(feature = "allocator_api"))]
+    bids_heap: BTreeMap<T, (f64, u64)>,
+    #[cfg(not(feature = "allocator_api"))]
+    _alloc: std::marker::PhantomData<A>,
+
+    #[cfg(feature = "allocator_api")]
+    asks_heap: BTreeMap<T, (f64, u64), A>,
+    #[cfg(feature = "allocator_api")]
+    bids_heap: BTreeMap<T, (f64, u64), A>,
+
+    heap_policy: HeapPolicy,
+    equal_sequence_policy: EqualSequencePolicy,
+    size_step: Option<f64>,
+    stats: BookStats,
+
+    stale: bool,
+    suppress_stale: bool,
+
+    spread_tracker: Option<SpreadTracker>,
+    totals: Option<Totals>,
+
+    #[cfg(feature = "recorder")]
+    recorder: crate::replay::Recorder,
+
+    #[cfg(feature = "price-memo")]
+    ask_prices: price_memo::PriceMemo<T>,
+    #[cfg(feature = "price-memo")]
+    bid_prices: price_memo::PriceMemo<T>,
 }
 
-impl<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize> std::fmt::Display
-    for OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS>
+/// book over `u64` ticks for instruments whose tick range exceeds `u32`
+pub type OrderBook64<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize> =
+    OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, u64>;
+
+/// book whose cache arrays live on the heap, for large `CACHE_SLOTS`. see
+/// `cache_storage`
+pub type BoxedOrderBook<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T = u32> =
+    OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, Global, Box<[f64; CACHE_SLOTS]>>;
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> std::fmt::Display for OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.render(f, format!("OrderBook @ {}", self.sequence_id))
+    }
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    /// renders like `Display` under a `"{label} @ {sequence_id}"` header,
+    /// followed by the last timestamp when there is one. borrows the book,
+    /// for telling symbols apart in shared logs
+    pub fn with_label<'a>(&'a self, label: &'a str) -> impl std::fmt::Display + 'a {
+        std::fmt::from_fn(move |f| {
+            let header = match self.last_timestamp {
+                Some(timestamp) => format!("{label} @ {} t={timestamp}", self.sequence_id),
+                None => format!("{label} @ {}", self.sequence_id),
+            };
+            self.render(f, header)
+        })
+    }
+
+    fn render(&self, f: &mut std::fmt::Formatter<'_>, header: String) -> std::fmt::Result {
         let asks = self.asks().rev();
         let bids = self.bids();
 
-        let levels = asks.chain(bids);
+        // prices at the book's decimals, not the full f64
+        let precision = self.tick_decimals.value() as usize;
+        let levels = asks.chain(bids).map(|level| RenderedLevel {
+            price: format!("{:.precision$}", level.price),
+            size: level.size,
+        });
 
         let table = Table::new(levels)
-            .with(Header::new(format!("OrderBook @ {}", self.sequence_id)))
+            .with(Header::new(header))
             .with(Style::modern_rounded())
             .to_string();
 
@@ -48,10 +284,149 @@ impl<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize> std::fmt::Display
     }
 }
 
-impl<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize>
-    OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS>
+/// running size sums per side, see `OrderBook::with_total_tracking`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Totals {
+    asks: f64,
+    bids: f64,
+}
+
+/// a table row of `Display`
+#[derive(Tabled)]
+struct RenderedLevel {
+    price: String,
+    size: f64,
+}
+
+/// empty book at 2 decimals, the common cent precision
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    C: CacheStorage<CACHE_SLOTS>,
+> Default for OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, Global, C>
+{
+    fn default() -> Self {
+        Self::new(crate::decimals!(2))
+    }
+}
+
+/// applies each update in order with `process_tick_update`
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> Extend<TickUpdate<T>> for OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    fn extend<I: IntoIterator<Item = TickUpdate<T>>>(&mut self, updates: I) {
+        for update in updates {
+            let _ = self.process_tick_update(&update);
+        }
+    }
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, Global, C>
 {
     pub fn new(tick_decimals: Decimals) -> Self {
+        Self::new_in(tick_decimals, Global)
+    }
+
+    /// `new` with the `Decimals` built from a plain integer, e.g. `try_new(2)`
+    pub fn try_new<N: TryInto<u8>>(tick_decimals: N) -> Result<Self, DecimalRangeError> {
+        Ok(Self::new(Decimals::new(tick_decimals)?))
+    }
+
+    /// empty book whose cache windows already sit around `price`, so a first
+    /// snapshot near it lands without a rebalance
+    pub fn with_reference_price(
+        tick_decimals: Decimals,
+        price: f64,
+    ) -> Result<Self, TickConversionError> {
+        let tick: T = tick_decimals.f64_to_tick(price)?;
+
+        let mut book = Self::new(tick_decimals);
+        book.asks_0_tick = tick.saturating_sub(T::from_index(CACHE_EMPTY_SLOTS));
+        book.bids_0_tick = tick.saturating_add(T::from_index(CACHE_EMPTY_SLOTS));
+        Ok(book)
+    }
+
+    /// builds a book straight from a snapshot, anchoring the cache windows on
+    /// the best levels and bulk loading the rest into the heaps
+    ///
+    /// invariant: asks sorted lowest to highest, bids highest to lowest
+    pub fn from_levels(
+        tick_decimals: Decimals,
+        sequence_id: u64,
+        asks: &[TickLevel<T>],
+        bids: &[TickLevel<T>],
+    ) -> Self {
+        let mut book = Self::new(tick_decimals);
+        book.sequence_id = sequence_id;
+
+        let mut asks = asks.iter().filter(|ask| populated(ask.size)).peekable();
+        if let Some(best_ask) = asks.peek() {
+            book.asks_0_tick = best_ask
+                .tick
+                .saturating_sub(T::from_index(CACHE_EMPTY_SLOTS));
+            book.best_ask_i = (best_ask.tick - book.asks_0_tick).to_index() as u16;
+        }
+        while let Some(ask) =
+            asks.next_if(|ask| (ask.tick - book.asks_0_tick).to_index() < CACHE_SLOTS)
+        {
+            book.asks[(ask.tick - book.asks_0_tick).to_index()] = ask.size;
+        }
+        book.asks_heap = asks
+            .map(|ask| (ask.tick, (ask.size, sequence_id)))
+            .collect();
+
+        let mut bids = bids.iter().filter(|bid| populated(bid.size)).peekable();
+        if let Some(best_bid) = bids.peek() {
+            book.bids_0_tick = best_bid
+                .tick
+                .saturating_add(T::from_index(CACHE_EMPTY_SLOTS));
+            book.best_bid_i = (book.bids_0_tick - best_bid.tick).to_index() as u16;
+        }
+        while let Some(bid) =
+            bids.next_if(|bid| (book.bids_0_tick - bid.tick).to_index() < CACHE_SLOTS)
+        {
+            book.bids[(book.bids_0_tick - bid.tick).to_index()] = bid.size;
+        }
+        book.bids_heap = bids
+            .map(|bid| (bid.tick, (bid.size, sequence_id)))
+            .collect();
+
+        book
+    }
+
+    /// fresh book with every update in `updates` applied in order, for
+    /// replaying a recorded session in tests and backtests
+    pub fn from_updates(
+        tick_decimals: Decimals,
+        updates: impl IntoIterator<Item = TickUpdate<T>>,
+    ) -> Self {
+        let mut book = Self::new(tick_decimals);
+        book.extend(updates);
+        book
+    }
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    /// book whose heaps allocate through `alloc`
+    pub fn new_in(tick_decimals: Decimals, alloc: A) -> Self {
         const {
             assert!(CACHE_SLOTS < u16::MAX as usize);
             assert!(CACHE_SLOTS > CACHE_EMPTY_SLOTS * 2);
@@ -60,743 +435,5071 @@ impl<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize>
         Self {
             tick_decimals,
             sequence_id: 0,
-            asks_0_tick: u32::MAX,
-            bids_0_tick: u32::MIN,
+            last_timestamp: None,
+            asks_0_tick: T::MAX,
+            bids_0_tick: T::MIN,
             best_ask_i: 0,
             best_bid_i: 0,
-            asks: [0.0; CACHE_SLOTS],
-            bids: [0.0; CACHE_SLOTS],
-            asks_heap: Default::default(),
-            bids_heap: Default::default(),
+            asks: C::zeroed(),
+            bids: C::zeroed(),
+            asks_heap: new_heap(&alloc),
+            bids_heap: new_heap(&alloc),
+            #[cfg(not(feature = "allocator_api"))]
+            _alloc: std::marker::PhantomData,
+            heap_policy: HeapPolicy::Keep,
+            equal_sequence_policy: EqualSequencePolicy::Apply,
+            size_step: None,
+            stats: BookStats::default(),
+            stale: false,
+            suppress_stale: false,
+            spread_tracker: None,
+            totals: None,
+            #[cfg(feature = "recorder")]
+            recorder: Default::default(),
+            #[cfg(feature = "price-memo")]
+            ask_prices: Default::default(),
+            #[cfg(feature = "price-memo")]
+            bid_prices: Default::default(),
         }
     }
 
-    pub fn best_bid(&self) -> FloatLevel {
-        FloatLevel {
+    /// NOTE: applies to levels leaving the window from now on. levels already
+    /// in the heap, e.g. loaded by `from_levels`, are kept
+    pub fn with_heap_policy(mut self, heap_policy: HeapPolicy) -> Self {
+        self.heap_policy = heap_policy;
+        self
+    }
+
+    /// NOTE: an empty book has nothing to resend over, an equal sequence is
+    /// always applied to it. a fresh book starts at sequence 0
+    pub fn with_equal_sequence_policy(mut self, policy: EqualSequencePolicy) -> Self {
+        self.equal_sequence_policy = policy;
+        self
+    }
+
+    /// analytics (`mid_price`, `spread` and co) return `None` while the book
+    /// is marked stale. the level accessors keep answering
+    pub fn with_stale_suppression(mut self, suppress: bool) -> Self {
+        self.suppress_stale = suppress;
+        self
+    }
+
+    /// records the spread after every update in a `SpreadTracker` holding
+    /// the last `window`. off by default, costing nothing but a branch
+    pub fn with_spread_tracking(mut self, window: usize) -> Self {
+        self.spread_tracker = Some(SpreadTracker::new(window));
+        self
+    }
+
+    /// keeps running size sums per side so `total_ask_size` and
+    /// `total_bid_size` are O(1), at the cost of an addition per written
+    /// level. the sums start from the levels already in the book
+    ///
+    /// NOTE: a running sum drifts by float rounding, compare with a tolerance
+    pub fn with_total_tracking(mut self) -> Self {
+        self.totals = Some(self.summed_totals());
+        self
+    }
+
+    /// rounds every incoming size to the nearest multiple of the venue lot
+    /// `step`, a size rounding to zero removes the level.
+    ///
+    /// NOTE: applies to updates from now on, levels already held are kept
+    pub fn with_size_step(mut self, step: f64) -> Self {
+        assert!(step > 0.0, "size step must be positive");
+        self.size_step = Some(step);
+        self
+    }
+
+    /// `None` when there are no bids
+    pub fn best_bid(&self) -> Option<FloatLevel> {
+        let size = self.bids[self.best_bid_i as usize];
+        if !populated(size) {
+            return None;
+        }
+
+        Some(FloatLevel {
             price: self
                 .tick_decimals
-                .fast_tick_to_f64(self.bids_0_tick - self.best_bid_i as u32),
-            size: self.bids[self.best_bid_i as usize],
-        }
+                .tick_to_f64(self.bids_0_tick - T::from_index(self.best_bid_i as usize)),
+            size,
+        })
     }
 
-    pub fn best_ask(&self) -> FloatLevel {
-        FloatLevel {
+    /// `None` when there are no asks
+    pub fn best_ask(&self) -> Option<FloatLevel> {
+        let size = self.asks[self.best_ask_i as usize];
+        if !populated(size) {
+            return None;
+        }
+
+        Some(FloatLevel {
             price: self
                 .tick_decimals
-                .fast_tick_to_f64(self.asks_0_tick + self.best_ask_i as u32),
-            size: self.asks[self.best_ask_i as usize],
+                .tick_to_f64(self.asks_0_tick + T::from_index(self.best_ask_i as usize)),
+            size,
+        })
+    }
+
+    /// `best_bid`, or `default` when there are no bids, for consumers that
+    /// want a continuous series
+    pub fn best_bid_or(&self, default: FloatLevel) -> FloatLevel {
+        self.best_bid().unwrap_or(default)
+    }
+
+    /// `best_ask`, or `default` when there are no asks
+    pub fn best_ask_or(&self, default: FloatLevel) -> FloatLevel {
+        self.best_ask().unwrap_or(default)
+    }
+
+    /// deepest known ask, `None` when there are no asks
+    pub fn worst_ask(&self) -> Option<FloatLevel> {
+        let (tick, size) = match self.asks_heap.last_key_value() {
+            Some((tick, (size, _))) => (*tick, *size),
+            None => {
+                let i = self.asks.iter().rposition(|sz| populated(*sz))?;
+                (self.asks_0_tick + T::from_index(i), self.asks[i])
+            }
+        };
+
+        Some(FloatLevel {
+            price: self.tick_decimals.tick_to_f64(tick),
+            size,
+        })
+    }
+
+    /// deepest known bid, `None` when there are no bids
+    pub fn worst_bid(&self) -> Option<FloatLevel> {
+        let (tick, size) = match self.bids_heap.first_key_value() {
+            Some((tick, (size, _))) => (*tick, *size),
+            None => {
+                let i = self.bids.iter().rposition(|sz| populated(*sz))?;
+                (self.bids_0_tick - T::from_index(i), self.bids[i])
+            }
+        };
+
+        Some(FloatLevel {
+            price: self.tick_decimals.tick_to_f64(tick),
+            size,
+        })
+    }
+
+    /// ask with the most size, ties go to the one nearest the best. O(n)
+    /// over cache and heap, keep it off the hot path
+    pub fn largest_ask(&self) -> Option<FloatLevel> {
+        self.size_extreme(Side::Ask, |size, extreme| size > extreme)
+    }
+
+    /// bid with the most size, see `largest_ask`
+    pub fn largest_bid(&self) -> Option<FloatLevel> {
+        self.size_extreme(Side::Bid, |size, extreme| size > extreme)
+    }
+
+    /// ask with the least size, for spotting dust. O(n), see `largest_ask`
+    pub fn smallest_ask(&self) -> Option<FloatLevel> {
+        self.size_extreme(Side::Ask, |size, extreme| size < extreme)
+    }
+
+    /// bid with the least size, see `smallest_ask`
+    pub fn smallest_bid(&self) -> Option<FloatLevel> {
+        self.size_extreme(Side::Bid, |size, extreme| size < extreme)
+    }
+
+    fn size_extreme(&self, side: Side, beats: impl Fn(f64, f64) -> bool) -> Option<FloatLevel> {
+        let mut extreme: Option<(T, f64)> = None;
+        for (tick, size) in self.side_entries(side) {
+            if extreme.is_none_or(|(_, extreme)| beats(size, extreme)) {
+                extreme = Some((tick, size));
+            }
         }
+
+        extreme.map(|(tick, size)| FloatLevel {
+            price: self.tick_decimals.tick_to_f64(tick),
+            size,
+        })
     }
 
-    pub fn asks(&self) -> impl DoubleEndedIterator<Item = FloatLevel> {
-        let asks_heap = self.asks_heap.iter().map(|(tick, size)| FloatLevel {
-            price: self.tick_decimals.fast_tick_to_f64(*tick),
-            size: *size,
-        });
+    /// raw tick of the best ask, `None` when there are no asks
+    #[inline]
+    pub fn best_ask_tick(&self) -> Option<T> {
+        (!self.ask_empty()).then(|| self.asks_0_tick + T::from_index(self.best_ask_i as usize))
+    }
 
-        let asks_cache = self
-            .asks
+    /// raw tick of the best bid, `None` when there are no bids
+    #[inline]
+    pub fn best_bid_tick(&self) -> Option<T> {
+        (!self.bid_empty()).then(|| self.bids_0_tick - T::from_index(self.best_bid_i as usize))
+    }
+
+    /// best ask tick minus best bid tick, `None` when a side is empty or the
+    /// book is crossed
+    pub fn spread_ticks(&self) -> Option<T> {
+        let ask = self.best_ask_tick()?;
+        let bid = self.best_bid_tick()?;
+        ask.checked_sub(bid)
+    }
+
+    /// ticks from the best ask to the next populated ask, 1 when they're
+    /// adjacent. `None` with fewer than 2 asks
+    pub fn ask_gap_ticks(&self) -> Option<T> {
+        let mut asks = self.ask_entries();
+        let (best, _) = asks.next()?;
+        let (next, _) = asks.next()?;
+        Some(next - best)
+    }
+
+    /// ticks from the best bid to the next populated bid, see `ask_gap_ticks`
+    pub fn bid_gap_ticks(&self) -> Option<T> {
+        let mut bids = self.bid_entries();
+        let (best, _) = bids.next()?;
+        let (next, _) = bids.next()?;
+        Some(best - next)
+    }
+
+    /// lowest and highest tick in the ask heap, `None` when nothing spilled
+    /// past the cache. O(log n)
+    #[inline]
+    pub fn ask_heap_bounds(&self) -> Option<(T, T)> {
+        let (first, _) = self.asks_heap.first_key_value()?;
+        let (last, _) = self.asks_heap.last_key_value()?;
+        Some((*first, *last))
+    }
+
+    /// lowest and highest tick in the bid heap, see `ask_heap_bounds`. the
+    /// highest is the one closest to the cache
+    #[inline]
+    pub fn bid_heap_bounds(&self) -> Option<(T, T)> {
+        let (first, _) = self.bids_heap.first_key_value()?;
+        let (last, _) = self.bids_heap.last_key_value()?;
+        Some((*first, *last))
+    }
+
+    /// no bids
+    #[inline]
+    pub fn bid_empty(&self) -> bool {
+        !populated(self.bids[self.best_bid_i as usize])
+    }
+
+    /// no asks
+    #[inline]
+    pub fn ask_empty(&self) -> bool {
+        !populated(self.asks[self.best_ask_i as usize])
+    }
+
+    /// no levels on either side
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bid_empty() && self.ask_empty()
+    }
+
+    /// at least one level on each side
+    #[inline]
+    pub fn has_both_sides(&self) -> bool {
+        !self.bid_empty() && !self.ask_empty()
+    }
+
+    /// a cache window has been anchored to the market, by a first update
+    /// with levels, `from_levels` or `with_reference_price`. until then both
+    /// anchors sit at the sentinels `T::MAX` and `T::MIN`. clearing the book
+    /// through `EqualSequencePolicy::Replace` resets them
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        self.asks_0_tick != T::MAX || self.bids_0_tick != T::MIN
+    }
+
+    /// raw `(tick, size)` asks, lowest to highest
+    ///
+    /// lazy: the heap is never cloned and is only entered once the cache
+    /// runs out, then walked only as far as the iterator is driven, see the
+    /// `asks_take_5` bench. the same holds for every level iterator built on
+    /// this or `bid_entries`. the iterator borrows the book, so the book can't
+    /// be updated while it's alive
+    ///
+    /// ```compile_fail,E0502
+    /// # use orderbook::{OrderBook, TickUpdate, decimals};
+    /// let mut book: OrderBook<4, 1> = OrderBook::new(decimals!(2));
+    /// let mut asks = book.ask_entries();
+    /// book.process_tick_update(&TickUpdate::default());
+    /// asks.next();
+    /// ```
+    pub fn ask_entries(&self) -> impl DoubleEndedIterator<Item = (T, f64)> {
+        let asks_heap = self
+            .asks_heap
+            .iter()
+            .map(|(tick, (size, _))| (*tick, *size));
+
+        self.ask_cache_levels().chain(asks_heap)
+    }
+
+    /// raw `(tick, size)` bids, highest to lowest
+    pub fn bid_entries(&self) -> impl DoubleEndedIterator<Item = (T, f64)> {
+        let bids_heap = self
+            .bids_heap
+            .iter()
+            .rev()
+            .map(|(tick, (size, _))| (*tick, *size));
+
+        self.bid_cache_levels().chain(bids_heap)
+    }
+
+    /// raw `(tick, size)` asks held in the cache window only, lowest to
+    /// highest. the heap is skipped, so deeper levels are missing unless
+    /// `CACHE_SLOTS` covers the depth needed
+    pub fn ask_cache_levels(&self) -> impl DoubleEndedIterator<Item = (T, f64)> {
+        self.asks
             .iter()
             .enumerate()
             .skip(self.best_ask_i as usize)
             .filter_map(|(i, sz)| {
-                if *sz < EPSILON {
+                if !populated(*sz) {
                     None
                 } else {
-                    Some(FloatLevel {
-                        price: self
-                            .tick_decimals
-                            .fast_tick_to_f64(self.asks_0_tick + i as u32),
-                        size: *sz,
-                    })
+                    Some((self.asks_0_tick + T::from_index(i), *sz))
                 }
-            });
-
-        asks_cache.chain(asks_heap)
+            })
     }
 
-    pub fn bids(&self) -> impl DoubleEndedIterator<Item = FloatLevel> {
-        let bids_cache = self
-            .bids
+    /// raw `(tick, size)` bids held in the cache window only, highest to
+    /// lowest, see `ask_cache_levels`
+    pub fn bid_cache_levels(&self) -> impl DoubleEndedIterator<Item = (T, f64)> {
+        self.bids
             .iter()
             .enumerate()
             .skip(self.best_bid_i as usize)
             .filter_map(|(i, sz)| {
-                if *sz < EPSILON {
+                if !populated(*sz) {
                     None
                 } else {
-                    Some(FloatLevel {
-                        price: self
-                            .tick_decimals
-                            .fast_tick_to_f64(self.bids_0_tick - i as u32),
-                        size: *sz,
-                    })
+                    Some((self.bids_0_tick - T::from_index(i), *sz))
                 }
-            });
+            })
+    }
 
-        let bids_heap = self.bids_heap.iter().rev().map(|(tick, size)| FloatLevel {
-            price: self.tick_decimals.fast_tick_to_f64(*tick),
-            size: *size,
-        });
+    /// top `n` asks as parallel `(prices, sizes)`, for plotting
+    pub fn ask_arrays(&self, n: usize) -> (Vec<f64>, Vec<f64>) {
+        self.asks().take(n).map(|l| (l.price, l.size)).unzip()
+    }
+
+    /// top `n` bids as parallel `(prices, sizes)`, for plotting
+    pub fn bid_arrays(&self, n: usize) -> (Vec<f64>, Vec<f64>) {
+        self.bids().take(n).map(|l| (l.price, l.size)).unzip()
+    }
+
+    /// every tick in `from_tick..=to_tick`, lowest first, with its ask size
+    /// or 0.0 where there's none. unlike `asks` empty ticks are yielded, for
+    /// fixed grid heatmaps. empty when `to_tick < from_tick`
+    pub fn ask_grid(&self, from_tick: T, to_tick: T) -> impl Iterator<Item = FloatLevel> {
+        grid(self.ask_entries(), from_tick, to_tick, self.tick_decimals)
+    }
+
+    /// every tick in `from_tick..=to_tick`, lowest first, with its bid size,
+    /// see `ask_grid`
+    pub fn bid_grid(&self, from_tick: T, to_tick: T) -> impl Iterator<Item = FloatLevel> {
+        grid(
+            self.bid_entries().rev(),
+            from_tick,
+            to_tick,
+            self.tick_decimals,
+        )
+    }
+
+    pub fn ask_ticks(&self) -> impl DoubleEndedIterator<Item = TickLevel<T>> {
+        self.ask_entries()
+            .map(|(tick, size)| TickLevel { tick, size })
+    }
+
+    pub fn bid_ticks(&self) -> impl DoubleEndedIterator<Item = TickLevel<T>> {
+        self.bid_entries()
+            .map(|(tick, size)| TickLevel { tick, size })
+    }
 
-        bids_cache.chain(bids_heap)
+    #[cfg(feature = "price-memo")]
+    #[inline]
+    fn invalidate_price_memo(&mut self) {
+        self.ask_prices.invalidate(self.asks_0_tick);
+        self.bid_prices.invalidate(self.bids_0_tick);
     }
 
     pub fn sequence_id(&self) -> u64 {
         self.sequence_id
     }
 
-    /// NOTE: update ordering not handled by book. this always updates book
-    pub fn process_tick_update(&mut self, update: &TickUpdate) {
-        self.sequence_id = update.sequence_id;
+    /// raw ask cache plus `asks_0_tick`: slot `i` holds the size at tick
+    /// `asks_0_tick + i`, ascending. sizes below `EPSILON` are empty slots.
+    /// the window keeps roughly `CACHE_EMPTY_SLOTS` empty slots below the best
+    /// ask as padding, and levels beyond the last slot live in the heap
+    #[cfg(feature = "internals")]
+    pub fn ask_cache(&self) -> (&[f64], T) {
+        (&self.asks[..], self.asks_0_tick)
+    }
 
-        // asks lowest -> highest
-        // bids highest -> lowest
+    /// raw bid cache plus `bids_0_tick`: slot `i` holds the size at tick
+    /// `bids_0_tick - i`, descending. sizes below `EPSILON` are empty slots.
+    /// the window keeps roughly `CACHE_EMPTY_SLOTS` empty slots above the best
+    /// bid as padding, and levels beyond the last slot live in the heap
+    #[cfg(feature = "internals")]
+    pub fn bid_cache(&self) -> (&[f64], T) {
+        (&self.bids[..], self.bids_0_tick)
+    }
 
-        // asks
-        let mut new_asks = update.asks();
-        if let Some(lowest_ask) = new_asks.next() {
-            if lowest_ask.tick < self.asks_0_tick {
-                self.rebalance_asks_lower(lowest_ask.tick);
-                self.best_ask_i = (lowest_ask.tick - self.asks_0_tick) as u16;
-            } else if lowest_ask.tick < self.best_ask_i as u32 + self.asks_0_tick {
-                self.best_ask_i = (lowest_ask.tick - self.asks_0_tick) as u16;
-            }
+    /// overwrites cache slot `i` of `side` as is.
+    ///
+    /// NOTE: no invariant is maintained, this exists to corrupt books for
+    /// testing `validate`
+    #[cfg(feature = "internals")]
+    pub fn set_cache_slot(&mut self, side: Side, i: usize, size: f64) {
+        match side {
+            Side::Ask => self.asks[i] = size,
+            Side::Bid => self.bids[i] = size,
+        }
+    }
+
+    /// overwrites the best index of `side` as is.
+    ///
+    /// NOTE: no invariant is maintained, see `set_cache_slot`
+    #[cfg(feature = "internals")]
+    pub fn set_best_index(&mut self, side: Side, i: u16) {
+        match side {
+            Side::Ask => self.best_ask_i = i,
+            Side::Bid => self.best_bid_i = i,
+        }
+    }
 
-            self.insert_ask(lowest_ask);
+    /// inserts a heap level of `side` as is.
+    ///
+    /// NOTE: no invariant is maintained, see `set_cache_slot`
+    #[cfg(feature = "internals")]
+    pub fn insert_heap_level(&mut self, side: Side, tick: T, size: f64) {
+        match side {
+            Side::Ask => self.asks_heap.insert(tick, (size, self.sequence_id)),
+            Side::Bid => self.bids_heap.insert(tick, (size, self.sequence_id)),
         };
+    }
 
-        for ask in new_asks {
-            self.insert_ask(ask);
+    /// tees every update passed to `process_tick_update` and its variants, or
+    /// `apply_batch`, into `writer` before applying it, in the
+    /// `replay` format. replaces any recorder already attached.
+    ///
+    /// `merge_snapshot`, `update_asks`/`update_bids` and `adjust_ask`/
+    /// `adjust_bid` are recorded as the update that reproduces them through
+    /// `process_tick_update`: only the levels a snapshot merge keeps, adjusted
+    /// sizes resolved to absolute ones. `retain_*`, `truncate_depth`,
+    /// `evict_stale` and `compact` aren't recorded.
+    ///
+    /// NOTE: those writes skip the `EqualSequencePolicy`, their records don't.
+    /// adjusts reuse the last sequence id, so replay is exact on books that
+    /// `Apply` equal sequences, the default
+    ///
+    /// a write error detaches the writer, `detach_recorder` reports it
+    #[cfg(feature = "recorder")]
+    pub fn attach_recorder(&mut self, writer: impl std::io::Write + Send + Sync + 'static) {
+        self.recorder.attach(writer);
+    }
+
+    /// records a write that bypassed `apply_update` as the update that
+    /// reproduces it, at the current sequence id
+    #[cfg(feature = "recorder")]
+    fn record_direct(
+        &mut self,
+        timestamp: Option<u64>,
+        asks: impl Iterator<Item = TickLevel<T>>,
+        bids: impl Iterator<Item = TickLevel<T>>,
+    ) {
+        if !self.recorder.is_attached() {
+            return;
         }
+        self.recorder.record(&TickUpdate {
+            sequence_id: self.sequence_id,
+            timestamp,
+            asks: asks.collect(),
+            bids: bids.collect(),
+        });
+    }
 
-        self.rebalance_asks_higher_and_update_best();
+    /// flushes and drops the recorder, returning the write error that
+    /// detached it if any
+    #[cfg(feature = "recorder")]
+    pub fn detach_recorder(&mut self) -> std::io::Result<()> {
+        self.recorder.detach()
+    }
 
-        // bids
-        let mut new_bids = update.bids();
-        if let Some(highest_bid) = new_bids.next() {
-            if highest_bid.tick > self.bids_0_tick {
-                self.rebalance_bids_higher(highest_bid.tick);
-                self.best_bid_i = (self.bids_0_tick - highest_bid.tick) as u16;
-            } else if highest_bid.tick > self.bids_0_tick - self.best_bid_i as u32 {
-                self.best_bid_i = (self.bids_0_tick - highest_bid.tick) as u16;
-            }
+    /// marks the book stale, e.g. during a trading halt, without touching the
+    /// levels. updates don't clear it, the caller does
+    pub fn set_stale(&mut self, stale: bool) {
+        self.stale = stale;
+    }
 
-            self.insert_bid(highest_bid);
-        };
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
 
-        for bid in new_bids {
-            self.insert_bid(bid);
-        }
+    /// whether analytics should hold back, see `with_stale_suppression`
+    #[inline]
+    pub(crate) fn suppressed(&self) -> bool {
+        self.stale && self.suppress_stale
+    }
 
-        self.rebalance_bids_lower_and_update_best();
+    /// `None` unless built `with_spread_tracking`. fed by every
+    /// `process_tick_update` and its variants, once per `apply_batch`
+    pub fn spread_tracker(&self) -> Option<&SpreadTracker> {
+        self.spread_tracker.as_ref()
     }
 
-    /// invariant: bid tick <= bids_0_tick
     #[inline]
-    fn insert_bid(&mut self, bid: TickLevel) {
-        debug_assert!(bid.tick <= self.bids_0_tick);
+    fn track_spread(&mut self) {
+        if self.spread_tracker.is_none() {
+            return;
+        }
+        let spread = self.spread_ticks();
+        if let (Some(tracker), Some(spread)) = (&mut self.spread_tracker, spread) {
+            tracker.push(spread.to_f64());
+        }
+    }
+
+    /// most recent timestamp carried by an update, in nanos
+    pub fn last_timestamp(&self) -> Option<u64> {
+        self.last_timestamp
+    }
 
-        let i = (self.bids_0_tick - bid.tick) as usize;
+    pub fn stats(&self) -> BookStats {
+        self.stats
+    }
 
-        // cache
-        if i < CACHE_SLOTS {
-            self.bids[i] = bid.size;
-        }
-        // heap escape - 0 size
-        else if bid.size < EPSILON {
-            self.bids_heap.remove(&bid.tick);
+    /// levels on both sides, cache and heaps
+    pub fn total_levels(&self) -> usize {
+        let cached = self
+            .asks
+            .iter()
+            .chain(self.bids.iter())
+            .filter(|sz| populated(**sz))
+            .count();
+
+        cached + self.asks_heap.len() + self.bids_heap.len()
+    }
+
+    /// size summed over every ask level. O(1) when built
+    /// `with_total_tracking`, a full walk otherwise
+    pub fn total_ask_size(&self) -> f64 {
+        match &self.totals {
+            Some(totals) => totals.asks,
+            None => self.ask_entries().map(|(_, size)| size).sum(),
         }
-        // heap escape - upsert
-        else {
-            self.bids_heap
-                .entry(bid.tick)
-                .and_modify(|sz| *sz = bid.size)
-                .or_insert(bid.size);
+    }
+
+    /// size summed over every bid level, see `total_ask_size`
+    pub fn total_bid_size(&self) -> f64 {
+        match &self.totals {
+            Some(totals) => totals.bids,
+            None => self.bid_entries().map(|(_, size)| size).sum(),
         }
     }
 
-    fn rebalance_bids_lower_and_update_best(&mut self) {
-        if self.bids[self.best_bid_i as usize] > EPSILON {
-            return;
+    fn summed_totals(&self) -> Totals {
+        Totals {
+            asks: self.ask_entries().map(|(_, size)| size).sum(),
+            bids: self.bid_entries().map(|(_, size)| size).sum(),
         }
+    }
 
-        // might be possible to start at best_bid_i as optimization
-        for i in 0..CACHE_SLOTS {
-            if self.bids[i] > EPSILON {
-                self.best_bid_i = i as u16;
-                break;
+    /// adds `delta` to the running sum of `side` when tracking totals
+    #[inline]
+    fn track_total(&mut self, side: Side, delta: f64) {
+        if let Some(totals) = &mut self.totals {
+            match side {
+                Side::Ask => totals.asks += delta,
+                Side::Bid => totals.bids += delta,
             }
         }
+    }
 
-        // rebalance
-        if self.best_bid_i > const { CACHE_EMPTY_SLOTS as u16 * 2 } {
-            let shift = self.best_bid_i - CACHE_EMPTY_SLOTS as u16;
-            self.bids_0_tick -= shift as u32;
-            self.best_bid_i -= shift;
-            for i in CACHE_EMPTY_SLOTS..(CACHE_SLOTS - shift as usize) {
-                self.bids[i] = self.bids[i + shift as usize]
-            }
-
-            for i in (CACHE_SLOTS - shift as usize)..CACHE_SLOTS {
-                let tick = self.bids_0_tick - i as u32;
-                if let Some(sz) = self.bids_heap.get(&tick) {
-                    self.bids[i] = *sz;
-                    self.bids_heap.remove(&tick);
-                } else {
-                    self.bids[i] = 0.0;
-                }
-            }
+    /// re-sums tracked totals after a bulk removal that's O(n) anyway
+    fn resum_totals(&mut self) {
+        if self.totals.is_some() {
+            self.totals = Some(self.summed_totals());
         }
     }
-    fn rebalance_asks_higher_and_update_best(&mut self) {
-        if self.asks[self.best_ask_i as usize] > EPSILON {
-            return;
+
+    /// hash of the observable state: sequence id, decimals and every level in
+    /// canonical order. books with the same levels hash equal no matter where
+    /// the levels sit in cache or heap.
+    ///
+    /// NOTE: stable within a build only, don't persist it
+    pub fn state_hash(&self) -> u64 {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.sequence_id.hash(&mut hasher);
+        self.tick_decimals.hash(&mut hasher);
+        for (tick, size) in self.ask_entries() {
+            tick.hash(&mut hasher);
+            size.to_bits().hash(&mut hasher);
         }
+        // separates the sides so a level can't hash the same on either
+        hasher.write_u8(0xff);
+        for (tick, size) in self.bid_entries() {
+            tick.hash(&mut hasher);
+            size.to_bits().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
 
-        // might be possible to start at best_ask_i as optimization
-        for i in 0..CACHE_SLOTS {
-            if self.asks[i] > EPSILON {
-                self.best_ask_i = i as u16;
-                break;
-            }
+    /// fraction of levels living in the heaps, 0.0 for an empty book.
+    /// staying high means the cache is too small for the instrument
+    pub fn heap_ratio(&self) -> f64 {
+        let total = self.total_levels();
+        if total == 0 {
+            return 0.0;
         }
 
-        if self.best_ask_i > const { CACHE_EMPTY_SLOTS as u16 * 2 } {
-            let shift = self.best_ask_i - CACHE_EMPTY_SLOTS as u16;
-            self.asks_0_tick += shift as u32;
-            self.best_ask_i -= shift;
+        (self.asks_heap.len() + self.bids_heap.len()) as f64 / total as f64
+    }
 
-            for i in CACHE_EMPTY_SLOTS..(CACHE_SLOTS - shift as usize) {
-                self.asks[i] = self.asks[i + shift as usize]
-            }
+    /// `(populated, CACHE_SLOTS)` ask cache slots, read next to the heap
+    /// length to tell a cache sized too big from one overflowing. scans the
+    /// cache
+    pub fn ask_cache_utilization(&self) -> (usize, usize) {
+        let used = self.asks.iter().filter(|sz| populated(**sz)).count();
+        (used, CACHE_SLOTS)
+    }
 
-            for i in (CACHE_SLOTS - shift as usize)..CACHE_SLOTS {
-                let tick = self.asks_0_tick + i as u32;
-                if let Some(sz) = self.asks_heap.get(&tick) {
-                    self.asks[i] = *sz;
-                    self.asks_heap.remove(&tick);
-                } else {
-                    self.asks[i] = 0.0;
+    /// `(populated, CACHE_SLOTS)` bid cache slots, see `ask_cache_utilization`
+    pub fn bid_cache_utilization(&self) -> (usize, usize) {
+        let used = self.bids.iter().filter(|sz| populated(**sz)).count();
+        (used, CACHE_SLOTS)
+    }
+
+    /// total opposite side size an incoming limit order on `side` would cross
+    ///
+    /// a `Side::Bid` (buy) crosses asks priced at or below `limit_price`,
+    /// a `Side::Ask` (sell) crosses bids priced at or above `limit_price`
+    pub fn marketable_size(&self, side: Side, limit_price: f64) -> f64 {
+        if limit_price.is_nan() {
+            return 0.0;
+        }
+
+        // tolerate float noise on prices that sit exactly on a tick
+        const TOLERANCE: f64 = 1e-9;
+        let scaled = limit_price * self.tick_decimals.grow_multiplier_f64();
+
+        match side {
+            Side::Bid => {
+                let limit_tick = (scaled + TOLERANCE).floor();
+                if limit_tick < 0.0 {
+                    return 0.0;
+                }
+                let limit_tick = T::from_f64(limit_tick);
+
+                let cache_size: f64 = if limit_tick < self.asks_0_tick {
+                    0.0
+                } else {
+                    let end = (limit_tick - self.asks_0_tick).to_index().saturating_add(1);
+                    self.asks
+                        .iter()
+                        .take(end)
+                        .skip(self.best_ask_i as usize)
+                        .filter(|sz| populated(**sz))
+                        .sum()
+                };
+
+                let heap_size: f64 = self
+                    .asks_heap
+                    .range(..=limit_tick)
+                    .map(|(_, (sz, _))| sz)
+                    .sum();
+
+                cache_size + heap_size
+            }
+            Side::Ask => {
+                let limit_tick = (scaled - TOLERANCE).ceil();
+                if limit_tick > T::MAX.to_f64() {
+                    return 0.0;
+                }
+                let limit_tick = T::from_f64(limit_tick);
+
+                let cache_size: f64 = if limit_tick > self.bids_0_tick {
+                    0.0
+                } else {
+                    let end = (self.bids_0_tick - limit_tick).to_index().saturating_add(1);
+                    self.bids
+                        .iter()
+                        .take(end)
+                        .skip(self.best_bid_i as usize)
+                        .filter(|sz| populated(**sz))
+                        .sum()
+                };
+
+                let heap_size: f64 = self
+                    .bids_heap
+                    .range(limit_tick..)
+                    .map(|(_, (sz, _))| sz)
+                    .sum();
+
+                cache_size + heap_size
+            }
+        }
+    }
+
+    /// same as `process_tick_update` but rejects the whole update, leaving the
+    /// book untouched, if any size is negative or NaN
+    pub fn try_process_tick_update(
+        &mut self,
+        update: &TickUpdate<T>,
+    ) -> Result<UpdateOutcome, UpdateError<T>> {
+        let asks = update.asks().map(|level| (Side::Ask, level));
+        let bids = update.bids().map(|level| (Side::Bid, level));
+
+        for (side, level) in asks.chain(bids) {
+            if level.size.is_nan() || level.size < 0.0 {
+                return Err(UpdateError::InvalidSize {
+                    side,
+                    tick: level.tick,
+                    size: level.size,
+                });
+            }
+        }
+
+        Ok(self.process_tick_update(update))
+    }
+
+    /// NOTE: update ordering not handled by book. this always updates book
+    ///
+    /// sizes must be non-negative: anything below `EPSILON`, negative sizes
+    /// included, removes the level. use `try_process_tick_update` to reject
+    /// negative sizes instead
+    ///
+    /// `sequence_id` advances even when the outcome is `Unchanged`
+    pub fn process_tick_update(&mut self, update: &TickUpdate<T>) -> UpdateOutcome {
+        self.apply_update(update, &mut ())
+    }
+
+    /// same as `process_tick_update` for feeds already in floats. every price
+    /// is rounded to the nearest tick with `Decimals::f64_to_tick`, then each
+    /// side is sorted and applied. levels need no particular order.
+    ///
+    /// prices off the tick grid are snapped silently, and two prices that
+    /// round to the same tick both write it, the later one in the slice
+    /// wins. a price that doesn't convert rejects the whole update, leaving
+    /// the book untouched
+    pub fn process_float_update(
+        &mut self,
+        sequence_id: u64,
+        asks: &[FloatLevel],
+        bids: &[FloatLevel],
+    ) -> Result<UpdateOutcome, TickConversionError> {
+        let to_ticks = |levels: &[FloatLevel]| {
+            levels
+                .iter()
+                .map(|level| level.to_tick(&self.tick_decimals))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let mut update = TickUpdate {
+            sequence_id,
+            timestamp: None,
+            asks: to_ticks(asks)?,
+            bids: to_ticks(bids)?,
+        };
+        // stable, duplicates keep their slice order
+        update.asks.sort_by_key(|level| level.tick);
+        update
+            .bids
+            .sort_by_key(|level| std::cmp::Reverse(level.tick));
+
+        Ok(self.process_tick_update(&update))
+    }
+
+    /// same as `process_tick_update`, also appends to `evicted` every level a
+    /// rebalance moved out of the cache into the heap. levels the heap policy
+    /// dropped instead are not reported
+    pub fn process_tick_update_with_evictions(
+        &mut self,
+        update: &TickUpdate<T>,
+        evicted: &mut Vec<(Side, T)>,
+    ) -> UpdateOutcome {
+        self.apply_update(update, evicted)
+    }
+
+    /// same as `process_tick_update`, also returns the `spread_ticks` after
+    /// the update so the caller can alarm on a widening spread
+    pub fn process_tick_update_with_spread(
+        &mut self,
+        update: &TickUpdate<T>,
+    ) -> (UpdateOutcome, Option<T>) {
+        let outcome = self.apply_update(update, &mut ());
+        (outcome, self.spread_ticks())
+    }
+
+    /// same as `process_tick_update`, returns the best bid and best ask from
+    /// before the update instead of the outcome, to diff against the new best
+    pub fn apply_capturing_prev(
+        &mut self,
+        update: &TickUpdate<T>,
+    ) -> (Option<FloatLevel>, Option<FloatLevel>) {
+        let prev = (self.best_bid(), self.best_ask());
+        let _ = self.apply_update(update, &mut ());
+        prev
+    }
+
+    /// same as `process_tick_update`, also reports every change it makes to
+    /// `sink` as a `BookEvent`
+    pub fn process_tick_update_with_events(
+        &mut self,
+        update: &TickUpdate<T>,
+        sink: &mut impl EventSink<T>,
+    ) -> UpdateOutcome {
+        self.apply_update(update, &mut Events(sink))
+    }
+
+    #[inline(always)]
+    fn apply_update(
+        &mut self,
+        update: &TickUpdate<T>,
+        observer: &mut impl Observer<T>,
+    ) -> UpdateOutcome {
+        #[cfg(feature = "recorder")]
+        self.recorder.record(update);
+
+        if update.sequence_id == self.sequence_id && !self.is_empty() {
+            match self.equal_sequence_policy {
+                EqualSequencePolicy::Apply => {}
+                EqualSequencePolicy::Ignore => return UpdateOutcome::Unchanged,
+                EqualSequencePolicy::Replace => self.clear_levels(),
+            }
+        }
+
+        self.sequence_id = update.sequence_id;
+        if update.timestamp.is_some() {
+            self.last_timestamp = update.timestamp;
+        }
+
+        let mut changed = self.apply_asks(update.asks(), observer);
+        changed |= self.apply_bids(update.bids(), observer);
+        self.track_spread();
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+
+        if changed {
+            UpdateOutcome::Changed
+        } else {
+            UpdateOutcome::Unchanged
+        }
+    }
+
+    /// merges a partial snapshot: every level in `update` is upserted, levels
+    /// the snapshot doesn't mention are kept.
+    ///
+    /// unlike `process_tick_update` a level below `EPSILON` is skipped
+    /// instead of removing the level, a snapshot only lists resting liquidity.
+    /// unlike `from_levels` nothing is cleared first
+    pub fn merge_snapshot(&mut self, update: &TickUpdate<T>) -> UpdateOutcome {
+        self.sequence_id = update.sequence_id;
+        if update.timestamp.is_some() {
+            self.last_timestamp = update.timestamp;
+        }
+
+        let live = |level: &TickLevel<T>| populated(level.size);
+        #[cfg(feature = "recorder")]
+        self.record_direct(
+            update.timestamp,
+            update.asks().filter(live),
+            update.bids().filter(live),
+        );
+
+        let mut changed = self.apply_asks(update.asks().filter(live), &mut ());
+        changed |= self.apply_bids(update.bids().filter(live), &mut ());
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+
+        if changed {
+            UpdateOutcome::Changed
+        } else {
+            UpdateOutcome::Unchanged
+        }
+    }
+
+    /// updates only the asks, for venues publishing each side on its own
+    /// channel. the bids are left as they are
+    ///
+    /// invariant: asks sorted lowest to highest
+    pub fn update_asks(&mut self, sequence_id: u64, asks: &[TickLevel<T>]) -> UpdateOutcome {
+        self.sequence_id = sequence_id;
+        #[cfg(feature = "recorder")]
+        self.record_direct(None, asks.iter().copied(), std::iter::empty());
+
+        let changed = self.apply_asks(asks.iter().copied(), &mut ());
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+
+        if changed {
+            UpdateOutcome::Changed
+        } else {
+            UpdateOutcome::Unchanged
+        }
+    }
+
+    /// updates only the bids, for venues publishing each side on its own
+    /// channel. the asks are left as they are
+    ///
+    /// invariant: bids sorted highest to lowest
+    pub fn update_bids(&mut self, sequence_id: u64, bids: &[TickLevel<T>]) -> UpdateOutcome {
+        self.sequence_id = sequence_id;
+        #[cfg(feature = "recorder")]
+        self.record_direct(None, std::iter::empty(), bids.iter().copied());
+
+        let changed = self.apply_bids(bids.iter().copied(), &mut ());
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+
+        if changed {
+            UpdateOutcome::Changed
+        } else {
+            UpdateOutcome::Unchanged
+        }
+    }
+
+    /// adds `delta` to the ask at `tick`, for feeds sending size changes
+    /// instead of absolute sizes. the result is clamped at 0, a level
+    /// subtracted below `EPSILON` is removed. `sequence_id` is left as
+    /// it is
+    pub fn adjust_ask(&mut self, tick: T, delta: f64) -> UpdateOutcome {
+        let size = self.ask_size_at(tick) + delta;
+        let size = if populated(size) { size } else { 0.0 };
+        #[cfg(feature = "recorder")]
+        self.record_direct(
+            None,
+            std::iter::once(TickLevel { tick, size }),
+            std::iter::empty(),
+        );
+
+        let changed = self.apply_asks(std::iter::once(TickLevel { tick, size }), &mut ());
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+
+        if changed {
+            UpdateOutcome::Changed
+        } else {
+            UpdateOutcome::Unchanged
+        }
+    }
+
+    /// adds `delta` to the bid at `tick`, see `adjust_ask`
+    pub fn adjust_bid(&mut self, tick: T, delta: f64) -> UpdateOutcome {
+        let size = self.bid_size_at(tick) + delta;
+        let size = if populated(size) { size } else { 0.0 };
+        #[cfg(feature = "recorder")]
+        self.record_direct(
+            None,
+            std::iter::empty(),
+            std::iter::once(TickLevel { tick, size }),
+        );
+
+        let changed = self.apply_bids(std::iter::once(TickLevel { tick, size }), &mut ());
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+
+        if changed {
+            UpdateOutcome::Changed
+        } else {
+            UpdateOutcome::Unchanged
+        }
+    }
+
+    fn ask_size_at(&self, tick: T) -> f64 {
+        let Some(i) = tick.checked_sub(self.asks_0_tick) else {
+            return 0.0;
+        };
+        match self.asks.get(i.to_index()) {
+            Some(size) => *size,
+            None => self.asks_heap.get(&tick).map_or(0.0, |(size, _)| *size),
+        }
+    }
+
+    fn bid_size_at(&self, tick: T) -> f64 {
+        let Some(i) = self.bids_0_tick.checked_sub(tick) else {
+            return 0.0;
+        };
+        match self.bids.get(i.to_index()) {
+            Some(size) => *size,
+            None => self.bids_heap.get(&tick).map_or(0.0, |(size, _)| *size),
+        }
+    }
+
+    /// applies a backlog of buffered updates in order, finalizing the best
+    /// indices and rebalancing once at the end instead of after every update.
+    /// ends in the same state as applying each with `process_tick_update`,
+    /// the cache window may sit elsewhere
+    ///
+    /// levels within an update need no particular order. `sequence_id` ends at
+    /// the highest in the batch. the `EqualSequencePolicy` applies per update
+    pub fn apply_batch(&mut self, updates: &[TickUpdate<T>]) -> UpdateOutcome {
+        let mut changed = false;
+
+        let size_step = self.size_step;
+        for update in updates {
+            #[cfg(feature = "recorder")]
+            self.recorder.record(update);
+
+            // best indices aren't final mid batch, count levels to tell empty
+            if update.sequence_id == self.sequence_id && self.total_levels() > 0 {
+                match self.equal_sequence_policy {
+                    EqualSequencePolicy::Apply => {}
+                    EqualSequencePolicy::Ignore => continue,
+                    EqualSequencePolicy::Replace => self.clear_levels(),
+                }
+            }
+
+            self.sequence_id = self.sequence_id.max(update.sequence_id);
+            if update.timestamp.is_some() {
+                self.last_timestamp = update.timestamp;
+            }
+
+            for ask in update.asks().map(|level| on_size_step(level, size_step)) {
+                // keeps the invariant the final rescan relies on: no populated
+                // slot below best_ask_i
+                if ask.tick < self.asks_0_tick {
+                    self.rebalance_asks_lower(ask.tick, &mut ());
+                    self.best_ask_i = (ask.tick - self.asks_0_tick).to_index() as u16;
+                } else if ask.tick < self.asks_0_tick + T::from_index(self.best_ask_i as usize) {
+                    self.best_ask_i = (ask.tick - self.asks_0_tick).to_index() as u16;
+                }
+                changed |= self.insert_ask(ask, &mut ());
+            }
+
+            for bid in update.bids().map(|level| on_size_step(level, size_step)) {
+                if bid.tick > self.bids_0_tick {
+                    self.rebalance_bids_higher(bid.tick, &mut ());
+                    self.best_bid_i = (self.bids_0_tick - bid.tick).to_index() as u16;
+                } else if bid.tick > self.bids_0_tick - T::from_index(self.best_bid_i as usize) {
+                    self.best_bid_i = (self.bids_0_tick - bid.tick).to_index() as u16;
                 }
+                changed |= self.insert_bid(bid, &mut ());
+            }
+        }
+
+        self.rebalance_asks_higher_and_update_best(self.best_ask_i as usize);
+        self.rebalance_bids_lower_and_update_best(self.best_bid_i as usize);
+        self.track_spread();
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+
+        if changed {
+            UpdateOutcome::Changed
+        } else {
+            UpdateOutcome::Unchanged
+        }
+    }
+
+    /// invariant: asks lowest -> highest
+    ///
+    /// returns whether any level changed
+    #[inline(always)]
+    fn apply_asks<O: Observer<T>>(
+        &mut self,
+        new_asks: impl Iterator<Item = TickLevel<T>>,
+        observer: &mut O,
+    ) -> bool {
+        // one copy per case. rounding inline with an unset step lets the
+        // compiler speculate the division on the unset payload, which can be
+        // a denormal and stall every level
+        match self.size_step {
+            Some(step) => self.apply_ask_levels(
+                new_asks.map(move |level| on_size_step(level, Some(step))),
+                observer,
+            ),
+            None => self.apply_ask_levels(new_asks, observer),
+        }
+    }
+
+    #[inline(always)]
+    fn apply_ask_levels<O: Observer<T>>(
+        &mut self,
+        new_asks: impl Iterator<Item = TickLevel<T>>,
+        observer: &mut O,
+    ) -> bool {
+        let mut new_asks = new_asks.peekable();
+        let mut changed = false;
+
+        // in-window fast path: levels behind a populated best and inside the
+        // cache can't move the best or the window, write them straight in
+        // and skip the rebalance. the steady state lands here entirely
+        if !self.ask_empty() {
+            let asks_0_tick = self.asks_0_tick;
+            let best_ask_tick = asks_0_tick + T::from_index(self.best_ask_i as usize);
+            let in_window = |ask: &TickLevel<T>| {
+                (ask.tick > best_ask_tick || ask.tick == best_ask_tick && populated(ask.size))
+                    && (ask.tick - asks_0_tick).to_index() < CACHE_SLOTS
+            };
+            while let Some(ask) = new_asks.next_if(in_window) {
+                changed |= self.insert_ask(ask, observer);
+            }
+            if new_asks.peek().is_none() {
+                return changed;
+            }
+        }
+
+        let before = O::EVENTS.then(|| (self.asks_0_tick, self.best_ask_tick()));
+
+        if let Some(lowest_ask) = new_asks.next() {
+            if lowest_ask.tick < self.asks_0_tick {
+                self.rebalance_asks_lower(lowest_ask.tick, observer);
+                self.best_ask_i = (lowest_ask.tick - self.asks_0_tick).to_index() as u16;
+            } else if lowest_ask.tick < T::from_index(self.best_ask_i as usize) + self.asks_0_tick {
+                self.best_ask_i = (lowest_ask.tick - self.asks_0_tick).to_index() as u16;
+            }
+
+            changed |= self.insert_ask(lowest_ask, observer);
+        };
+
+        // levels after the first are worse than the best unless the update
+        // breaks the ordering invariant, then the rescan has to start at the
+        // lowest slot written in front of the best
+        let best_ask_tick = self.asks_0_tick + T::from_index(self.best_ask_i as usize);
+        let mut lowest_tick = best_ask_tick;
+        for ask in new_asks {
+            lowest_tick = lowest_tick.min(ask.tick);
+            changed |= self.insert_ask(ask, observer);
+        }
+
+        self.rebalance_asks_higher_and_update_best((lowest_tick - self.asks_0_tick).to_index());
+
+        if let Some((asks_0_tick, best)) = before {
+            if self.asks_0_tick != asks_0_tick {
+                observer.event(|| BookEvent::Rebalanced {
+                    side: Side::Ask,
+                    from_0_tick: asks_0_tick,
+                    to_0_tick: self.asks_0_tick,
+                });
+            }
+            let new_best = self.best_ask_tick();
+            if new_best != best {
+                observer.event(|| BookEvent::BestChanged {
+                    side: Side::Ask,
+                    tick: new_best,
+                });
+            }
+        }
+
+        changed
+    }
+
+    /// invariant: bids highest -> lowest
+    ///
+    /// returns whether any level changed
+    #[inline(always)]
+    fn apply_bids<O: Observer<T>>(
+        &mut self,
+        new_bids: impl Iterator<Item = TickLevel<T>>,
+        observer: &mut O,
+    ) -> bool {
+        // see `apply_asks`
+        match self.size_step {
+            Some(step) => self.apply_bid_levels(
+                new_bids.map(move |level| on_size_step(level, Some(step))),
+                observer,
+            ),
+            None => self.apply_bid_levels(new_bids, observer),
+        }
+    }
+
+    #[inline(always)]
+    fn apply_bid_levels<O: Observer<T>>(
+        &mut self,
+        new_bids: impl Iterator<Item = TickLevel<T>>,
+        observer: &mut O,
+    ) -> bool {
+        let mut new_bids = new_bids.peekable();
+        let mut changed = false;
+
+        // in-window fast path, see `apply_asks`
+        if !self.bid_empty() {
+            let bids_0_tick = self.bids_0_tick;
+            let best_bid_tick = bids_0_tick - T::from_index(self.best_bid_i as usize);
+            let in_window = |bid: &TickLevel<T>| {
+                (bid.tick < best_bid_tick || bid.tick == best_bid_tick && populated(bid.size))
+                    && (bids_0_tick - bid.tick).to_index() < CACHE_SLOTS
+            };
+            while let Some(bid) = new_bids.next_if(in_window) {
+                changed |= self.insert_bid(bid, observer);
+            }
+            if new_bids.peek().is_none() {
+                return changed;
+            }
+        }
+
+        let before = O::EVENTS.then(|| (self.bids_0_tick, self.best_bid_tick()));
+
+        if let Some(highest_bid) = new_bids.next() {
+            if highest_bid.tick > self.bids_0_tick {
+                self.rebalance_bids_higher(highest_bid.tick, observer);
+                self.best_bid_i = (self.bids_0_tick - highest_bid.tick).to_index() as u16;
+            } else if highest_bid.tick > self.bids_0_tick - T::from_index(self.best_bid_i as usize)
+            {
+                self.best_bid_i = (self.bids_0_tick - highest_bid.tick).to_index() as u16;
+            }
+
+            changed |= self.insert_bid(highest_bid, observer);
+        };
+
+        let best_bid_tick = self.bids_0_tick - T::from_index(self.best_bid_i as usize);
+        let mut highest_tick = best_bid_tick;
+        for bid in new_bids {
+            highest_tick = highest_tick.max(bid.tick);
+            changed |= self.insert_bid(bid, observer);
+        }
+
+        self.rebalance_bids_lower_and_update_best((self.bids_0_tick - highest_tick).to_index());
+
+        if let Some((bids_0_tick, best)) = before {
+            if self.bids_0_tick != bids_0_tick {
+                observer.event(|| BookEvent::Rebalanced {
+                    side: Side::Bid,
+                    from_0_tick: bids_0_tick,
+                    to_0_tick: self.bids_0_tick,
+                });
+            }
+            let new_best = self.best_bid_tick();
+            if new_best != best {
+                observer.event(|| BookEvent::BestChanged {
+                    side: Side::Bid,
+                    tick: new_best,
+                });
+            }
+        }
+
+        changed
+    }
+
+    /// recenters both cache windows on their best with `CACHE_EMPTY_SLOTS`
+    /// padding, whether or not the automatic rebalance would have. a window
+    /// moving away from its best pulls the heap levels it uncovers into the
+    /// cache, one moving towards it spills its far end like a rebalance
+    /// would, heap policy included
+    pub fn compact(&mut self) {
+        if let Some(best) = self.best_ask_tick() {
+            if self.best_ask_i as usize > CACHE_EMPTY_SLOTS {
+                self.shift_asks_higher();
+            } else if best.saturating_sub(T::from_index(CACHE_EMPTY_SLOTS)) < self.asks_0_tick {
+                self.rebalance_asks_lower(best, &mut ());
+                self.best_ask_i = (best - self.asks_0_tick).to_index() as u16;
+            }
+        }
+
+        if let Some(best) = self.best_bid_tick() {
+            if self.best_bid_i as usize > CACHE_EMPTY_SLOTS {
+                self.shift_bids_lower();
+            } else if best.saturating_add(T::from_index(CACHE_EMPTY_SLOTS)) > self.bids_0_tick {
+                self.rebalance_bids_higher(best, &mut ());
+                self.best_bid_i = (self.bids_0_tick - best).to_index() as u16;
+            }
+        }
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+    }
+
+    /// empties both sides and resets the windows as on a fresh book
+    fn clear_levels(&mut self) {
+        self.asks_0_tick = T::MAX;
+        self.bids_0_tick = T::MIN;
+        self.best_ask_i = 0;
+        self.best_bid_i = 0;
+        self.asks.fill(0.0);
+        self.bids.fill(0.0);
+        self.asks_heap.clear();
+        self.bids_heap.clear();
+        self.resum_totals();
+    }
+
+    /// removes every ask level for which `f(tick, size)` returns false
+    pub fn retain_asks(&mut self, f: impl Fn(T, f64) -> bool) {
+        for i in 0..CACHE_SLOTS {
+            if populated(self.asks[i]) && !f(self.asks_0_tick + T::from_index(i), self.asks[i]) {
+                self.asks[i] = 0.0;
+            }
+        }
+        self.asks_heap.retain(|tick, (sz, _)| f(*tick, *sz));
+
+        self.rebalance_asks_higher_and_update_best(self.best_ask_i as usize);
+        self.resum_totals();
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+    }
+
+    /// removes every bid level for which `f(tick, size)` returns false
+    pub fn retain_bids(&mut self, f: impl Fn(T, f64) -> bool) {
+        for i in 0..CACHE_SLOTS {
+            if populated(self.bids[i]) && !f(self.bids_0_tick - T::from_index(i), self.bids[i]) {
+                self.bids[i] = 0.0;
+            }
+        }
+        self.bids_heap.retain(|tick, (sz, _)| f(*tick, *sz));
+
+        self.rebalance_bids_lower_and_update_best(self.best_bid_i as usize);
+        self.resum_totals();
+
+        #[cfg(feature = "price-memo")]
+        self.invalidate_price_memo();
+    }
+
+    /// keeps only the best `n` levels of each side, dropping the rest from
+    /// the cache and the heap
+    pub fn truncate_depth(&mut self, n: usize) {
+        let dropped_ask = self.ask_ticks().nth(n);
+        if let Some(dropped) = dropped_ask {
+            self.retain_asks(|tick, _| tick < dropped.tick);
+        }
+        let dropped_bid = self.bid_ticks().nth(n);
+        if let Some(dropped) = dropped_bid {
+            self.retain_bids(|tick, _| tick > dropped.tick);
+        }
+    }
+
+    /// drops every heap level last written by an update with a sequence id
+    /// below `older_than_seq`, returns how many. for far liquidity the feed
+    /// stopped refreshing. cache levels are kept, so the best never moves.
+    ///
+    /// NOTE: cache slots carry no sequence id, a level moved into the heap by
+    /// a rebalance or `compact` is stamped with the sequence id current then,
+    /// however long ago the feed last wrote it. a window that just moved
+    /// makes the levels it pushed out look fresh
+    ///
+    /// NOTE: to know when it was written every heap level stores the sequence
+    /// id next to its size, 8 more bytes per level
+    pub fn evict_stale(&mut self, older_than_seq: u64) -> usize {
+        let before = self.asks_heap.len() + self.bids_heap.len();
+        self.asks_heap.retain(|_, (_, seq)| *seq >= older_than_seq);
+        self.bids_heap.retain(|_, (_, seq)| *seq >= older_than_seq);
+        self.resum_totals();
+        before - self.asks_heap.len() - self.bids_heap.len()
+    }
+
+    /// invariant: bid tick <= bids_0_tick
+    ///
+    /// returns whether the stored size changed
+    #[inline]
+    fn insert_bid(&mut self, bid: TickLevel<T>, observer: &mut impl Observer<T>) -> bool {
+        debug_assert!(bid.tick <= self.bids_0_tick);
+
+        let i = (self.bids_0_tick - bid.tick).to_index();
+
+        // negative and dust sizes are stored as a removal, so tracked totals
+        // move by what the slot really holds
+        let size = if populated(bid.size) { bid.size } else { 0.0 };
+
+        let (old, new) =
+            // cache
+            if i < CACHE_SLOTS {
+                (std::mem::replace(&mut self.bids[i], size), size)
             }
+            // heap escape - 0 size
+            else if size == 0.0 {
+                (self.bids_heap.remove(&bid.tick).map_or(0.0, |(size, _)| size), 0.0)
+            }
+            // heap escape - upsert
+            else if self
+                .spills(self.bids_0_tick - T::from_index(self.best_bid_i as usize) - bid.tick)
+            {
+                let old = self.bids_heap.insert(bid.tick, (size, self.sequence_id));
+                (old.map_or(0.0, |(size, _)| size), size)
+            }
+            // heap escape - dropped by policy
+            else {
+                (self.bids_heap.remove(&bid.tick).map_or(0.0, |(size, _)| size), 0.0)
+            };
+
+        self.track_total(Side::Bid, new - old);
+
+        let changed = (old - new).abs() > EPSILON;
+        if changed {
+            observer.event(|| level_event(Side::Bid, bid.tick, old, new));
+        }
+        changed
+    }
+
+    /// invariant: no populated bid slot below `start`, start <= best_bid_i.
+    /// start is below the best when a level landed in front of it
+    ///
+    /// the best only needs a rescan when it was removed or a level landed in
+    /// front of it. removing any deeper level, in cache or heap, leaves the
+    /// best where it is
+    fn rebalance_bids_lower_and_update_best(&mut self, start: usize) {
+        // a populated best is only final when no level landed in front of it
+        if start == self.best_bid_i as usize && populated(self.bids[start]) {
+            return;
+        }
+
+        let Some(i) = self.bids[start..]
+            .iter()
+            .position(|sz| populated(*sz))
+            .map(|i| start + i)
+        else {
+            // cache ran dry, the best bid (if any) now lives in the heap
+            self.refill_bids_from_heap();
+            return;
+        };
+        self.best_bid_i = i as u16;
+
+        // rebalance
+        if self.best_bid_i > const { CACHE_EMPTY_SLOTS as u16 * 2 } {
+            self.shift_bids_lower();
+        }
+    }
+
+    /// invariant: best_bid_i > CACHE_EMPTY_SLOTS
+    ///
+    /// moves the window down until the best sits at `CACHE_EMPTY_SLOTS`,
+    /// pulling the heap levels it uncovers into the cache
+    fn shift_bids_lower(&mut self) {
+        let shift = self.best_bid_i - CACHE_EMPTY_SLOTS as u16;
+        self.bids_0_tick -= T::from_index(shift as usize);
+        self.best_bid_i -= shift;
+        for i in CACHE_EMPTY_SLOTS..(CACHE_SLOTS - shift as usize) {
+            self.bids[i] = self.bids[i + shift as usize]
+        }
+
+        for i in (CACHE_SLOTS - shift as usize)..CACHE_SLOTS {
+            // slots below tick 0 can never hold a level
+            let sz = self
+                .bids_0_tick
+                .checked_sub(T::from_index(i))
+                .and_then(|tick| self.bids_heap.remove(&tick));
+            self.bids[i] = sz.map_or(0.0, |(size, _)| size);
+        }
+    }
+
+    /// invariant: no populated ask slot below `start`, start <= best_ask_i.
+    /// start is below the best when a level landed in front of it
+    ///
+    /// the best only needs a rescan when it was removed or a level landed in
+    /// front of it. removing any deeper level, in cache or heap, leaves the
+    /// best where it is
+    fn rebalance_asks_higher_and_update_best(&mut self, start: usize) {
+        // a populated best is only final when no level landed in front of it
+        if start == self.best_ask_i as usize && populated(self.asks[start]) {
+            return;
+        }
+
+        let Some(i) = self.asks[start..]
+            .iter()
+            .position(|sz| populated(*sz))
+            .map(|i| start + i)
+        else {
+            // cache ran dry, the best ask (if any) now lives in the heap
+            self.refill_asks_from_heap();
+            return;
+        };
+        self.best_ask_i = i as u16;
+
+        if self.best_ask_i > const { CACHE_EMPTY_SLOTS as u16 * 2 } {
+            self.shift_asks_higher();
+        }
+    }
+
+    /// invariant: best_ask_i > CACHE_EMPTY_SLOTS
+    ///
+    /// moves the window up until the best sits at `CACHE_EMPTY_SLOTS`,
+    /// pulling the heap levels it uncovers into the cache
+    fn shift_asks_higher(&mut self) {
+        let shift = self.best_ask_i - CACHE_EMPTY_SLOTS as u16;
+        self.asks_0_tick += T::from_index(shift as usize);
+        self.best_ask_i -= shift;
+
+        for i in CACHE_EMPTY_SLOTS..(CACHE_SLOTS - shift as usize) {
+            self.asks[i] = self.asks[i + shift as usize]
+        }
+
+        for i in (CACHE_SLOTS - shift as usize)..CACHE_SLOTS {
+            // slots above T::MAX can never hold a level
+            let sz = self
+                .asks_0_tick
+                .checked_add(T::from_index(i))
+                .and_then(|tick| self.asks_heap.remove(&tick));
+            self.asks[i] = sz.map_or(0.0, |(size, _)| size);
         }
     }
 
-    /// invariant: ask tick >= asks_0_tick
-    #[inline]
-    fn insert_ask(&mut self, ask: TickLevel) {
-        debug_assert!(ask.tick >= self.asks_0_tick);
+    /// invariant: bid cache holds no populated slot, any left are overwritten
+    ///
+    /// re-anchors the cache on the best heap bid with `CACHE_EMPTY_SLOTS`
+    /// padding and moves every heap level inside the new window into the cache.
+    /// leaves the side empty when the heap is empty too
+    fn refill_bids_from_heap(&mut self) {
+        let Some((&best_tick, _)) = self.bids_heap.last_key_value() else {
+            return;
+        };
+
+        self.bids.fill(0.0);
+        self.bids_0_tick = best_tick.saturating_add(T::from_index(CACHE_EMPTY_SLOTS));
+        self.best_bid_i = (self.bids_0_tick - best_tick).to_index() as u16;
+
+        while let Some(entry) = self.bids_heap.last_entry() {
+            let i = (self.bids_0_tick - *entry.key()).to_index();
+            if i >= CACHE_SLOTS {
+                break;
+            }
+            self.bids[i] = entry.remove().0;
+        }
+    }
+
+    /// invariant: ask cache holds no populated slot, any left are overwritten
+    ///
+    /// re-anchors the cache on the best heap ask with `CACHE_EMPTY_SLOTS`
+    /// padding and moves every heap level inside the new window into the cache.
+    /// leaves the side empty when the heap is empty too
+    fn refill_asks_from_heap(&mut self) {
+        let Some((&best_tick, _)) = self.asks_heap.first_key_value() else {
+            return;
+        };
+
+        self.asks.fill(0.0);
+        self.asks_0_tick = best_tick.saturating_sub(T::from_index(CACHE_EMPTY_SLOTS));
+        self.best_ask_i = (best_tick - self.asks_0_tick).to_index() as u16;
+
+        while let Some(entry) = self.asks_heap.first_entry() {
+            let i = (*entry.key() - self.asks_0_tick).to_index();
+            if i >= CACHE_SLOTS {
+                break;
+            }
+            self.asks[i] = entry.remove().0;
+        }
+    }
+
+    /// invariant: ask tick >= asks_0_tick
+    ///
+    /// returns whether the stored size changed
+    #[inline]
+    fn insert_ask(&mut self, ask: TickLevel<T>, observer: &mut impl Observer<T>) -> bool {
+        debug_assert!(ask.tick >= self.asks_0_tick);
+
+        let i = (ask.tick - self.asks_0_tick).to_index();
+
+        // negative and dust sizes are stored as a removal, so tracked totals
+        // move by what the slot really holds
+        let size = if populated(ask.size) { ask.size } else { 0.0 };
+
+        let (old, new) =
+            // cache
+            if i < CACHE_SLOTS {
+                (std::mem::replace(&mut self.asks[i], size), size)
+            }
+            // heap escape - 0 size
+            else if size == 0.0 {
+                (self.asks_heap.remove(&ask.tick).map_or(0.0, |(size, _)| size), 0.0)
+            }
+            // heap escape - upsert
+            else if self
+                .spills(ask.tick - (self.asks_0_tick + T::from_index(self.best_ask_i as usize)))
+            {
+                let old = self.asks_heap.insert(ask.tick, (size, self.sequence_id));
+                (old.map_or(0.0, |(size, _)| size), size)
+            }
+            // heap escape - dropped by policy
+            else {
+                (self.asks_heap.remove(&ask.tick).map_or(0.0, |(size, _)| size), 0.0)
+            };
+
+        self.track_total(Side::Ask, new - old);
+
+        let changed = (old - new).abs() > EPSILON;
+        if changed {
+            observer.event(|| level_event(Side::Ask, ask.tick, old, new));
+        }
+        changed
+    }
+
+    /// whether a level `distance` ticks from the best belongs in the heap.
+    /// counts the level as a dropped spill when it doesn't
+    #[inline]
+    fn spills(&mut self, distance: T) -> bool {
+        let spills = match self.heap_policy {
+            HeapPolicy::Keep => true,
+            HeapPolicy::DropBeyond(n) => distance <= T::from(n),
+            HeapPolicy::Disabled => false,
+        };
+        if !spills {
+            self.stats.dropped_spills += 1;
+        }
+        spills
+    }
+
+    /// invariant: highest_tick + CACHE_EMPTY_SLOTS > self.bids_0_tick, the
+    /// window moves up. `compact` moves it onto a best already inside
+    ///
+    /// enforces invariant: highest_tick <= bids_0_tick
+    #[inline]
+    fn rebalance_bids_higher(&mut self, highest_tick: T, observer: &mut impl Observer<T>) {
+        let new_bids_0_tick = highest_tick.saturating_add(T::from_index(CACHE_EMPTY_SLOTS));
+        debug_assert!(new_bids_0_tick > self.bids_0_tick);
+
+        let shift = (new_bids_0_tick - self.bids_0_tick).to_index();
+
+        // rebuild cache
+        let i_eviction_start: usize = CACHE_SLOTS.saturating_sub(shift);
+
+        for i in i_eviction_start..CACHE_SLOTS {
+            // TODO: can replace with next initialized tick offsets
+            if populated(self.bids[i]) {
+                let tick = self.bids_0_tick - T::from_index(i);
+                if self.spills(highest_tick - tick) {
+                    self.bids_heap
+                        .insert(tick, (self.bids[i], self.sequence_id));
+                    observer.evicted(Side::Bid, tick);
+                } else {
+                    self.track_total(Side::Bid, -self.bids[i]);
+                }
+
+                self.bids[i] = 0.0
+            }
+        }
+
+        for i in (0..i_eviction_start).rev() {
+            self.bids[i + shift] = self.bids[i];
+            self.bids[i] = 0.0;
+        }
+
+        self.bids_0_tick = new_bids_0_tick;
+    }
+
+    /// invariant: lowest_tick - CACHE_EMPTY_SLOTS < self.asks_0_tick, the
+    /// window moves down. `compact` moves it onto a best already inside
+    ///
+    /// enforces invariant: lowest_tick >= asks_0_tick
+    #[inline]
+    fn rebalance_asks_lower(&mut self, lowest_tick: T, observer: &mut impl Observer<T>) {
+        let new_asks_0_tick = lowest_tick.saturating_sub(T::from_index(CACHE_EMPTY_SLOTS));
+        debug_assert!(new_asks_0_tick < self.asks_0_tick);
+
+        let shift = (self.asks_0_tick - new_asks_0_tick).to_index();
+
+        // rebuild cache
+        let i_eviction_start: usize = CACHE_SLOTS.saturating_sub(shift);
+
+        for i in i_eviction_start..CACHE_SLOTS {
+            // TODO: can replace with next initialized tick offsets
+            if populated(self.asks[i]) {
+                let tick = T::from_index(i) + self.asks_0_tick;
+                if self.spills(tick - lowest_tick) {
+                    self.asks_heap
+                        .insert(tick, (self.asks[i], self.sequence_id));
+                    observer.evicted(Side::Ask, tick);
+                } else {
+                    self.track_total(Side::Ask, -self.asks[i]);
+                }
+
+                self.asks[i] = 0.0
+            }
+        }
+
+        for i in (0..i_eviction_start).rev() {
+            self.asks[i + shift] = self.asks[i];
+            self.asks[i] = 0.0;
+        }
+
+        self.asks_0_tick = new_asks_0_tick;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn best_ask() {
+        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(2, 5.0)],
+            bids: vec![],
+        });
+
+        let best_ask = book.best_ask().unwrap();
+
+        assert_eq!(best_ask.price, 0.02);
+        assert_eq!(best_ask.size, 5.0);
+    }
+
+    #[test]
+    fn best_bid() {
+        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(1, 10.0)],
+        });
+
+        let best_bid = book.best_bid().unwrap();
+
+        assert_eq!(best_bid.price, 0.01);
+        assert_eq!(best_bid.size, 10.0);
+    }
+
+    #[test]
+    fn initialized_after_first_update() {
+        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert!(!book.is_initialized());
+
+        // nothing to position on
+        let _ = book.process_tick_update(&TickUpdate::default());
+        assert!(!book.is_initialized());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 10.0)],
+        });
+        println!("{book:#?}");
+        assert!(book.is_initialized());
+
+        let book: OrderBook<3, 1> =
+            OrderBook::with_reference_price(2u8.try_into().unwrap(), 1.0).unwrap();
+        assert!(book.is_initialized());
+    }
+
+    #[test]
+    fn labeled_display() {
+        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 4,
+            timestamp: None,
+            asks: vec![tl(101, 5.0)],
+            bids: vec![tl(99, 10.0)],
+        });
+
+        let rendered = book.with_label("BTC-USD").to_string();
+        println!("{rendered}");
+        assert!(rendered.contains("BTC-USD @ 4"));
+        assert!(!rendered.contains("t="));
+        assert!(book.to_string().contains("OrderBook @ 4"));
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 5,
+            timestamp: Some(1_700_000_000),
+            asks: vec![],
+            bids: vec![],
+        });
+        assert!(
+            book.with_label("BTC-USD")
+                .to_string()
+                .contains("BTC-USD @ 5 t=1700000000")
+        );
+    }
+
+    #[test]
+    fn display_prices_at_decimals() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(30, 5.0), tl(100, 1.0)],
+            bids: vec![tl(2, 10.0)],
+        });
+
+        let rendered = book.to_string();
+        println!("{rendered}");
+        assert_eq!(book.best_ask().unwrap().price, 0.3);
+        assert!(rendered.contains(" 0.02 "));
+        assert!(rendered.contains(" 0.30 "));
+        assert!(rendered.contains(" 1.00 "));
+
+        // 3 * 0.1 is 0.30000000000000004 as a float
+        let mut book: OrderBook<4, 1> = OrderBook::new(1u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(3, 1.0)],
+            bids: vec![],
+        });
+        let rendered = book.to_string();
+        assert!(rendered.contains(" 0.3 "));
+        assert!(!rendered.contains("0.30000000000000004"));
+    }
+
+    #[test]
+    fn try_new() {
+        let book: OrderBook<3, 1> = OrderBook::try_new(2).unwrap();
+        assert_eq!(book.tick_decimals, Decimals::new(2u8).unwrap());
+        assert!(OrderBook::<3, 1>::try_new(18u64).is_ok());
+
+        assert_eq!(
+            OrderBook::<3, 1>::try_new(19).unwrap_err(),
+            DecimalRangeError
+        );
+        assert_eq!(
+            OrderBook::<3, 1>::try_new(-1).unwrap_err(),
+            DecimalRangeError
+        );
+    }
+
+    #[test]
+    fn init() {
+        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(103, 25.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
+        });
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.sequence_id, 0);
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.best_ask_i, 1);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.asks[0], 0.0);
+        assert_eq!(book.asks[1], 5.0);
+        assert_eq!(book.asks[2], 15.0);
+        assert_eq!(book.bids[0], 0.0);
+        assert_eq!(book.bids[1], 10.0);
+        assert_eq!(book.bids[2], 20.0);
+        assert_eq!(book.asks_heap.len(), 1);
+        assert_eq!(book.asks_heap.get(&103).map(|(size, _)| *size), Some(25.0));
+        assert_eq!(book.bids_heap.len(), 1);
+        assert_eq!(book.bids_heap.get(&97).map(|(size, _)| *size), Some(30.0));
+    }
+
+    #[test]
+    fn update_order_book_with_level_removal_and_addition() {
+        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let init = TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0)],
+            bids: vec![tl(99, 10.0)],
+        };
+
+        let _ = book.process_tick_update(&init);
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.asks[1], 5.0); // tick 101
+        assert_eq!(book.asks_heap.len(), 0);
+
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.bids[1], 10.0); // tick 99
+        assert_eq!(book.bids_heap.len(), 0);
+
+        let update = TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, 15.0)],
+            bids: vec![tl(99, 0.0), tl(98, 20.0)],
+        };
+
+        let _ = book.process_tick_update(&update);
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.asks[1], 0.0); // tick 101 (removed)
+        assert_eq!(book.asks[2], 15.0); // tick 102 (added)
+        assert_eq!(book.asks_heap.len(), 0);
+
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.bids[1], 0.0); // tick 99 (removed)
+        assert_eq!(book.bids[2], 20.0); // tick 98 (added)
+        assert_eq!(book.bids_heap.len(), 0);
+    }
+
+    #[test]
+    fn test_rebalance_bids_higher() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
+        });
+
+        println!("Before rebalance:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.bids[1], 10.0); // tick 99
+        assert_eq!(book.bids[2], 20.0); // tick 98
+        assert_eq!(book.bids[3], 30.0); // tick 97
+        assert_eq!(book.bids_heap.len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(101, 15.0)],
+        });
+
+        println!("After rebalance higher:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.bids_0_tick, 102);
+        assert_eq!(book.bids[0], 0.0); // tick 102 (empty)
+        assert_eq!(book.bids[1], 15.0); // tick 101 (new)
+        assert_eq!(book.bids[2], 0.0); // tick 100 (empty)
+        assert_eq!(book.bids[3], 10.0); // tick 99 (shifted)
+        assert_eq!(book.bids_heap.get(&98).unwrap().0, 20.0);
+        assert_eq!(book.bids_heap.get(&97).unwrap().0, 30.0);
+        assert_eq!(book.bids_heap.len(), 2);
+    }
+
+    #[test]
+    fn test_rebalance_asks_lower() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
+            bids: vec![],
+        });
+
+        println!("Before rebalance:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.asks[1], 5.0); // tick 101
+        assert_eq!(book.asks[2], 20.0); // tick 102
+        assert_eq!(book.asks[3], 30.0); // tick 103
+        assert_eq!(book.asks_heap.len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(99, 15.0)],
+            bids: vec![],
+        });
+
+        println!("After rebalance lower:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 98);
+        assert_eq!(book.asks[0], 0.0); // tick 98 (empty)
+        assert_eq!(book.asks[1], 15.0); // tick 99 (new)
+        assert_eq!(book.asks[2], 0.0); // tick 100 (empty)
+        assert_eq!(book.asks[3], 5.0); // tick 101 (shifted)
+        assert_eq!(book.asks_heap.get(&102).unwrap().0, 20.0);
+        assert_eq!(book.asks_heap.get(&103).unwrap().0, 30.0);
+        assert_eq!(book.asks_heap.len(), 2);
+    }
+
+    #[test]
+    fn test_rebalance_bids_lower_and_update_best() {
+        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
+        });
+
+        println!("Initial state:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.bids[1], 10.0); // tick 99
+        assert_eq!(book.bids[2], 20.0); // tick 98
+        assert_eq!(book.bids[3], 30.0); // tick 97
+        assert_eq!(book.bids_heap.len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![
+                tl(99, 0.0),   // Remove
+                tl(98, 0.0),   // Remove
+                tl(97, 35.0),  // Update size
+                tl(95, 50.0),  // Add new
+                tl(86, 100.0), // Add new much lower
+            ],
+        });
+
+        println!("After rebalance lower:\n{book:#?}");
+        println!("{book}");
+        assert_eq!(book.bids_0_tick, 98);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.bids[0], 0.0);
+        assert_eq!(book.bids[1], 35.0); // tick 97
+        assert_eq!(book.bids[2], 0.0);
+        assert_eq!(book.bids[3], 50.0); // tick 95
+        assert_eq!(book.bids_heap.get(&86).unwrap().0, 100.0);
+        assert_eq!(book.bids_heap.len(), 1);
+    }
+
+    #[test]
+    fn rescan_falls_back_on_unordered_levels() {
+        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(103, 30.0)],
+            bids: vec![tl(99, 10.0), tl(97, 30.0)],
+        });
+
+        // removes the best, then writes a better level out of order
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(100, 7.0)],
+            bids: vec![tl(99, 0.0), tl(100, 8.0)],
+        });
+
+        println!("{book:#?}");
+
+        assert_eq!(book.best_ask().unwrap().size, 7.0);
+        assert_eq!(book.best_ask_i, 0);
+        assert_eq!(book.best_bid().unwrap().size, 8.0);
+        assert_eq!(book.best_bid_i, 0);
+    }
+
+    #[test]
+    fn best_lowered_and_old_best_zeroed() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(103, 5.0), tl(105, 30.0)],
+            bids: vec![tl(97, 10.0), tl(95, 30.0)],
+        });
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (101, 2));
+        assert_eq!((book.bids_0_tick, book.best_bid_i), (99, 2));
+
+        // new best inside the window and the old best removed in one update
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(102, 7.0), tl(103, 0.0)],
+            bids: vec![tl(98, 8.0), tl(97, 0.0)],
+        });
+
+        println!("{book:#?}");
+
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (101, 1));
+        assert_eq!((book.bids_0_tick, book.best_bid_i), (99, 1));
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(102, 7.0), (105, 30.0)]
+        );
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(98, 8.0), (95, 30.0)]
+        );
+
+        // same with the levels out of order, the old best staying populated
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(105, 31.0), tl(101, 1.0)],
+            bids: vec![tl(95, 31.0), tl(99, 1.0)],
+        });
+        assert_eq!(book.best_ask_i, 0);
+        assert_eq!(book.best_bid_i, 0);
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rescan_starts_at_hint() {
+        let mut book: OrderBook<64, 8> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(1010, 5.0), tl(1030, 30.0)],
+            bids: vec![tl(990, 10.0), tl(970, 30.0)],
+        });
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (1002, 8));
+        assert_eq!((book.bids_0_tick, book.best_bid_i), (998, 8));
+
+        // better levels out of order, the old best staying populated. the
+        // lowest written slot is the new best, not just any written slot
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(1030, 31.0), tl(1007, 2.0), tl(1004, 1.0), tl(1003, 0.0)],
+            bids: vec![tl(970, 31.0), tl(993, 2.0), tl(996, 1.0), tl(997, 0.0)],
+        });
+
+        println!("{book:#?}");
+
+        assert_eq!(book.best_ask_tick(), Some(1004));
+        assert_eq!(book.best_bid_tick(), Some(996));
+        assert_eq!(book.validate(), Ok(()));
+
+        // a level in front of the best written with zero size leaves it alone
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(1030, 30.0), tl(1003, 0.0)],
+            bids: vec![tl(970, 30.0), tl(997, 0.0)],
+        });
+        assert_eq!(book.best_ask_tick(), Some(1004));
+        assert_eq!(book.best_bid_tick(), Some(996));
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_rebalance_asks_higher_and_update_best() {
+        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
+            bids: vec![],
+        });
+
+        println!("Initial state:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.asks[1], 5.0); // tick 101
+        assert_eq!(book.asks[2], 20.0); // tick 102
+        assert_eq!(book.asks[3], 30.0); // tick 103
+        assert_eq!(book.asks_heap.len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![
+                tl(101, 0.0),   // Remove
+                tl(102, 0.0),   // Remove
+                tl(103, 35.0),  // Update size
+                tl(105, 50.0),  // Add new
+                tl(114, 100.0), // Add new much higher
+            ],
+            bids: vec![],
+        });
+
+        println!("After rebalance higher:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 102);
+        assert_eq!(book.best_ask_i, 1);
+
+        assert_eq!(book.asks[0], 0.0);
+        assert_eq!(book.asks[1], 35.0); // tick 103
+        assert_eq!(book.asks[2], 0.0);
+        assert_eq!(book.asks[3], 50.0); // tick 105
+        assert_eq!(book.asks_heap.get(&114).unwrap().0, 100.0);
+        assert_eq!(book.asks_heap.len(), 1);
+    }
+
+    #[test]
+    fn test_new_best_ask_i_lower_without_rebalance() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
+            bids: vec![],
+        });
+
+        println!("Initial state:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.best_ask_i, 1);
+        assert_eq!(book.asks[1], 5.0); // tick 101
+        assert_eq!(book.asks[2], 20.0); // tick 102
+        assert_eq!(book.asks[3], 30.0); // tick 103
+        assert_eq!(book.asks_heap.len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(100, 1.0)],
+            bids: vec![],
+        });
+
+        println!("After rebalance higher:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.best_ask_i, 0);
+        assert_eq!(book.asks[0], 1.0); // tick 100
+    }
+
+    #[test]
+    fn test_new_best_ask_i_higher_without_rebalance() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
+            bids: vec![],
+        });
+
+        println!("Initial state:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.best_ask_i, 1);
+        assert_eq!(book.asks[1], 5.0); // tick 101
+        assert_eq!(book.asks[2], 20.0); // tick 102
+        assert_eq!(book.asks[3], 30.0); // tick 103
+        assert_eq!(book.asks_heap.len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0)],
+            bids: vec![],
+        });
+
+        println!("After rebalance higher:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.best_ask_i, 2);
+        assert_eq!(book.asks[1], 0.0); // tick 101
+        assert_eq!(book.asks[2], 20.0); // tick 102
+    }
+
+    #[test]
+    fn test_new_best_bid_i_higher_without_rebalance() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 5.0), tl(98, 20.0), tl(97, 30.0)],
+        });
+
+        println!("Initial state:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.bids[1], 5.0); // tick 99
+        assert_eq!(book.bids[2], 20.0); // tick 98
+        assert_eq!(book.bids[3], 30.0); // tick 97
+        assert_eq!(book.bids_heap.len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(100, 1.0)],
+        });
+
+        println!("After update:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.best_bid_i, 0);
+        assert_eq!(book.bids[0], 1.0); // tick 100
+    }
+
+    #[test]
+    fn best_bid_at_index_0_removed() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let bids = |book: &mut OrderBook<4, 1>, seq, bids| {
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: seq,
+                timestamp: None,
+                asks: vec![],
+                bids,
+            });
+            println!("{book:#?}");
+            assert_eq!(book.validate(), Ok(()));
+        };
+
+        bids(
+            &mut book,
+            0,
+            vec![tl(99, 5.0), tl(98, 20.0), tl(97, 30.0), tl(90, 1.0)],
+        );
+        bids(&mut book, 1, vec![tl(100, 1.0)]);
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.best_bid_i, 0);
+
+        // rescan from the padding slot, best still close enough to stay put
+        bids(&mut book, 2, vec![tl(100, 0.0)]);
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.bids[0], 0.0);
+        assert_eq!(book.best_bid_tick(), Some(99));
+
+        // back at index 0 and removed again together with the next level
+        bids(&mut book, 3, vec![tl(100, 2.0)]);
+        assert_eq!(book.best_bid_i, 0);
+        bids(&mut book, 4, vec![tl(100, 0.0), tl(99, 0.0)]);
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.best_bid_i, 2);
+        assert_eq!(book.best_bid_tick(), Some(98));
+
+        // the next removal moves the best past the padding and shifts
+        bids(&mut book, 5, vec![tl(98, 0.0)]);
+        assert_eq!(book.bids_0_tick, 98);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.bids[..], [0.0, 30.0, 0.0, 0.0]);
+        assert_eq!(book.bids_heap.len(), 1);
+
+        // cache runs dry, refilled from the heap
+        bids(&mut book, 6, vec![tl(97, 0.0)]);
+        assert_eq!(book.bids_0_tick, 91);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.best_bid_tick(), Some(90));
+        assert!(book.bids_heap.is_empty());
+    }
+
+    #[test]
+    fn best_ask_at_index_0_removed() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
+            bids: vec![],
+        });
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(100, 1.0)],
+            bids: vec![],
+        });
+        assert_eq!(book.best_ask_i, 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(100, 0.0), tl(101, 0.0), tl(102, 0.0)],
+            bids: vec![],
+        });
+        println!("{book:#?}");
+
+        assert_eq!(book.asks_0_tick, 102);
+        assert_eq!(book.best_ask_i, 1);
+        assert_eq!(book.asks[..], [0.0, 30.0, 0.0, 0.0]);
+        assert_eq!(book.best_ask_tick(), Some(103));
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_new_best_bid_i_lower_without_rebalance() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 5.0), tl(98, 20.0), tl(97, 30.0)],
+        });
+
+        println!("Initial state:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.bids[1], 5.0); // tick 99
+        assert_eq!(book.bids[2], 20.0); // tick 98
+        assert_eq!(book.bids[3], 30.0); // tick 97
+        assert_eq!(book.bids_heap.len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 0.0)],
+        });
+
+        println!("After update:\n{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.bids_0_tick, 100);
+        assert_eq!(book.best_bid_i, 2);
+        assert_eq!(book.bids[1], 0.0); // tick 99
+        assert_eq!(book.bids[2], 20.0); // tick 98
+    }
+
+    /// walks the market up and down in steps of every size around the window
+    /// and checks the book against a plain map after each update
+    fn drive_minimum_config<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize>() {
+        use std::collections::BTreeMap;
+
+        let mut book: OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS> =
+            OrderBook::new(2u8.try_into().unwrap());
+        let mut asks = BTreeMap::new();
+        let mut bids = BTreeMap::new();
+
+        let steps = (1..=CACHE_SLOTS as i64 + 2).flat_map(|step| [step, -step, step, step, -step]);
+        let mut mid: i64 = 1000;
+        for (sequence_id, step) in steps.enumerate() {
+            mid += step;
+            let m = mid as u32;
+            let size = sequence_id as f64 + 1.0;
+
+            let mut update = TickUpdate {
+                sequence_id: sequence_id as u64,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![],
+            };
+            // levels that would cross the new mid are removed
+            let mut ask_levels: BTreeMap<u32, f64> =
+                asks.range(..=m).map(|(tick, _)| (*tick, 0.0)).collect();
+            let mut bid_levels: BTreeMap<u32, f64> =
+                bids.range(m..).map(|(tick, _)| (*tick, 0.0)).collect();
+            for offset in [1, 2, 4] {
+                ask_levels.insert(m + offset, size);
+                bid_levels.insert(m - offset, size);
+            }
+            update.asks = ask_levels.iter().map(|(t, sz)| tl(*t, *sz)).collect();
+            update.bids = bid_levels.iter().rev().map(|(t, sz)| tl(*t, *sz)).collect();
+
+            for level in &update.asks {
+                if level.size < EPSILON {
+                    asks.remove(&level.tick);
+                } else {
+                    asks.insert(level.tick, level.size);
+                }
+            }
+            for level in &update.bids {
+                if level.size < EPSILON {
+                    bids.remove(&level.tick);
+                } else {
+                    bids.insert(level.tick, level.size);
+                }
+            }
+
+            let _ = book.process_tick_update(&update);
+
+            assert_eq!(book.validate(), Ok(()), "step {step} to mid {m}");
+            assert_eq!(
+                book.ask_entries().collect::<Vec<_>>(),
+                asks.iter().map(|(t, sz)| (*t, *sz)).collect::<Vec<_>>(),
+                "step {step} to mid {m}"
+            );
+            assert_eq!(
+                book.bid_entries().collect::<Vec<_>>(),
+                bids.iter()
+                    .rev()
+                    .map(|(t, sz)| (*t, *sz))
+                    .collect::<Vec<_>>(),
+                "step {step} to mid {m}"
+            );
+        }
+
+        println!("{book:#?}");
+    }
+
+    #[test]
+    fn minimum_configs() {
+        drive_minimum_config::<3, 1>();
+        drive_minimum_config::<5, 2>();
+        drive_minimum_config::<7, 3>();
+    }
+
+    #[test]
+    fn boxed_cache_matches_inline() {
+        let mut inline: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        let mut boxed: BoxedOrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        assert!(size_of_val(&boxed) < size_of_val(&inline));
+
+        // drifting mids force rebalances both ways and spills into the heaps.
+        // stale levels are left behind, the book crosses along the way
+        let mut mid = 1000u32;
+        for (seq, step) in [0i32, 3, 7, -12, 1, -20, 15, 40, -2, 0]
+            .into_iter()
+            .enumerate()
+        {
+            mid = mid.wrapping_add_signed(step);
+            let update = TickUpdate {
+                sequence_id: seq as u64,
+                timestamp: None,
+                asks: (1..6).map(|i| tl(mid + i * 2, i as f64)).collect(),
+                bids: (1..6).map(|i| tl(mid - i * 3, i as f64 + 0.5)).collect(),
+            };
+            assert_eq!(
+                inline.process_tick_update(&update),
+                boxed.process_tick_update(&update)
+            );
+            assert_eq!(
+                inline.ask_cache_levels().count(),
+                boxed.ask_cache_levels().count()
+            );
+            assert_eq!(inline.snapshot(), boxed.snapshot());
+        }
+
+        println!("{boxed:#?}");
+        assert_eq!(format!("{inline:?}"), format!("{boxed:?}"));
+
+        let clone = boxed.clone();
+        let _ = boxed.process_tick_update(&TickUpdate {
+            sequence_id: 10,
+            timestamp: None,
+            asks: vec![tl(mid + 2, 0.0)],
+            bids: vec![],
+        });
+        assert_ne!(clone.snapshot(), boxed.snapshot());
+    }
+
+    #[test]
+    fn reference_price_avoids_first_rebalance() {
+        let mut book: OrderBook<8, 2> =
+            OrderBook::with_reference_price(2u8.try_into().unwrap(), 1.00).unwrap();
+        assert!(book.is_empty());
+        assert_eq!((book.asks_0_tick, book.bids_0_tick), (98, 102));
+
+        let mut evicted = vec![];
+        let _ = book.process_tick_update_with_evictions(
+            &TickUpdate {
+                sequence_id: 0,
+                timestamp: None,
+                asks: vec![tl(101, 5.0), tl(102, 15.0)],
+                bids: vec![tl(99, 10.0), tl(98, 20.0)],
+            },
+            &mut evicted,
+        );
+
+        println!("{book:#?}");
+
+        // windows didn't move
+        assert_eq!((book.asks_0_tick, book.bids_0_tick), (98, 102));
+        assert_eq!((book.best_ask_i, book.best_bid_i), (3, 3));
+        assert!(evicted.is_empty());
+        assert_eq!(book.best_ask_tick(), Some(101));
+        assert_eq!(book.best_bid_tick(), Some(99));
+
+        assert_eq!(
+            OrderBook::<8, 2>::with_reference_price(2u8.try_into().unwrap(), f64::NAN).err(),
+            Some(TickConversionError::NotFinite)
+        );
+
+        // past the u32 tick range, only a u64 book anchors there
+        let price = 50_000_000.0;
+        assert_eq!(
+            OrderBook::<8, 2>::with_reference_price(2u8.try_into().unwrap(), price).err(),
+            Some(TickConversionError::OutOfRange)
+        );
+        let book: OrderBook64<8, 2> =
+            OrderBook64::with_reference_price(2u8.try_into().unwrap(), price).unwrap();
+        assert_eq!(
+            (book.asks_0_tick, book.bids_0_tick),
+            (4_999_999_998, 5_000_000_002)
+        );
+    }
+
+    #[test]
+    fn levels_at_anchor_ticks() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(110, 5.0)],
+            bids: vec![tl(90, 10.0)],
+        });
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (108, 2));
+        assert_eq!((book.bids_0_tick, book.best_bid_i), (92, 2));
+
+        // exactly on the anchors: index 0, no rebalance
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(108, 1.0)],
+            bids: vec![tl(92, 2.0)],
+        });
+
+        println!("{book:#?}");
+
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (108, 0));
+        assert_eq!((book.bids_0_tick, book.best_bid_i), (92, 0));
+        assert_eq!(book.asks[0], 1.0);
+        assert_eq!(book.bids[0], 2.0);
+        assert_eq!(book.best_ask_tick(), Some(108));
+        assert_eq!(book.best_bid_tick(), Some(92));
+
+        // one past the anchors rebalances with fresh padding
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(107, 3.0)],
+            bids: vec![tl(93, 4.0)],
+        });
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (105, 2));
+        assert_eq!((book.bids_0_tick, book.best_bid_i), (95, 2));
+        assert_eq!(book.validate(), Ok(()));
+
+        // removing the anchor level leaves the side on its next level
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 3,
+            timestamp: None,
+            asks: vec![tl(107, 0.0), tl(108, 0.0)],
+            bids: vec![tl(93, 0.0), tl(92, 0.0)],
+        });
+        assert_eq!(book.best_ask_tick(), Some(110));
+        assert_eq!(book.best_bid_tick(), Some(90));
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[test]
+    fn ask_anchor_saturates_at_zero() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+
+        // no room for padding below tick 1, the best sits past index 0
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(1, 5.0)],
+            bids: vec![],
+        });
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (0, 1));
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(0, 1.0)],
+            bids: vec![],
+        });
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (0, 0));
+        assert_eq!(book.best_ask_tick(), Some(0));
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[test]
+    fn zeroing_deeper_level_keeps_best() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
+        });
+        let (best_ask_i, best_bid_i) = (book.best_ask_i, book.best_bid_i);
+        let (best_ask, best_bid) = (book.best_ask(), book.best_bid());
+
+        // cache levels
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(102, 0.0)],
+            bids: vec![tl(98, 0.0)],
+        });
+        assert_eq!((book.best_ask_i, book.best_bid_i), (best_ask_i, best_bid_i));
+        assert_eq!((book.best_ask(), book.best_bid()), (best_ask, best_bid));
+
+        // heap levels
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(110, 0.0)],
+            bids: vec![tl(90, 0.0)],
+        });
+
+        println!("{book:#?}");
+
+        assert_eq!((book.best_ask_i, book.best_bid_i), (best_ask_i, best_bid_i));
+        assert_eq!((book.best_ask(), book.best_bid()), (best_ask, best_bid));
+        assert!(book.asks_heap.is_empty() && book.bids_heap.is_empty());
+        assert_eq!(book.total_levels(), 2);
+    }
+
+    #[test]
+    fn marketable_size() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(103, 25.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0), tl(90, 40.0)],
+        });
+
+        assert_eq!(book.asks_heap.len(), 1);
+        assert_eq!(book.bids_heap.len(), 1);
+
+        // buys
+        assert_eq!(book.marketable_size(Side::Bid, 1.00), 0.0);
+        assert_eq!(book.marketable_size(Side::Bid, 1.01), 5.0);
+        assert_eq!(book.marketable_size(Side::Bid, 1.025), 20.0);
+        assert_eq!(book.marketable_size(Side::Bid, 1.03), 45.0);
+        assert_eq!(book.marketable_size(Side::Bid, 1.09), 45.0);
+        assert_eq!(book.marketable_size(Side::Bid, 1.10), 80.0);
+        assert_eq!(book.marketable_size(Side::Bid, 1e12), 80.0);
+
+        // sells
+        assert_eq!(book.marketable_size(Side::Ask, 1.00), 0.0);
+        assert_eq!(book.marketable_size(Side::Ask, 0.99), 10.0);
+        assert_eq!(book.marketable_size(Side::Ask, 0.975), 30.0);
+        assert_eq!(book.marketable_size(Side::Ask, 0.97), 60.0);
+        assert_eq!(book.marketable_size(Side::Ask, 0.90), 100.0);
+        assert_eq!(book.marketable_size(Side::Ask, -1.0), 100.0);
+    }
+
+    #[test]
+    fn marketable_size_empty_book() {
+        let book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        assert_eq!(book.marketable_size(Side::Bid, 1e12), 0.0);
+        assert_eq!(book.marketable_size(Side::Ask, 0.0), 0.0);
+        assert_eq!(book.marketable_size(Side::Bid, f64::NAN), 0.0);
+    }
+
+    #[test]
+    fn marketable_size_limit_before_best() {
+        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        });
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0)],
+            bids: vec![tl(99, 0.0)],
+        });
+
+        assert_eq!(book.best_ask_i, 2);
+        assert_eq!(book.best_bid_i, 2);
+        assert_eq!(book.marketable_size(Side::Bid, 1.01), 0.0);
+        assert_eq!(book.marketable_size(Side::Ask, 0.99), 0.0);
+    }
+
+    #[test]
+    fn cleared_side_is_empty() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        });
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, 0.0)],
+            bids: vec![tl(99, 0.0), tl(98, 0.0)],
+        });
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        assert!(book.best_ask().is_none());
+        assert!(book.best_bid().is_none());
+        assert_eq!(book.asks().count(), 0);
+        assert_eq!(book.bids().count(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(102, 7.0)],
+            bids: vec![tl(98, 8.0)],
+        });
+
+        assert_eq!(book.best_ask().unwrap().price, 1.02);
+        assert_eq!(book.best_bid().unwrap().price, 0.98);
+    }
+
+    #[test]
+    fn cleared_cache_refills_from_heap() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(110, 15.0), tl(111, 25.0)],
+            bids: vec![tl(99, 10.0), tl(90, 20.0), tl(89, 30.0)],
+        });
+
+        assert_eq!(book.asks_heap.len(), 2);
+        assert_eq!(book.bids_heap.len(), 2);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0)],
+            bids: vec![tl(99, 0.0)],
+        });
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        let best_ask = book.best_ask().unwrap();
+        assert_eq!(best_ask.price, 1.10);
+        assert_eq!(best_ask.size, 15.0);
+        assert_eq!(book.asks_0_tick, 109);
+        assert_eq!(book.best_ask_i, 1);
+        assert_eq!(book.asks[2], 25.0); // tick 111
+        assert_eq!(book.asks_heap.len(), 0);
+
+        let best_bid = book.best_bid().unwrap();
+        assert_eq!(best_bid.price, 0.90);
+        assert_eq!(best_bid.size, 20.0);
+        assert_eq!(book.bids_0_tick, 91);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.bids[2], 30.0); // tick 89
+        assert_eq!(book.bids_heap.len(), 0);
+    }
+
+    #[test]
+    fn heap_policy_keep() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(105, 15.0), tl(120, 25.0)],
+            bids: vec![tl(99, 10.0), tl(95, 20.0), tl(80, 30.0)],
+        });
+
+        assert_eq!(book.asks_heap.len(), 2);
+        assert_eq!(book.bids_heap.len(), 2);
+        assert_eq!(book.asks().count(), 3);
+        assert_eq!(book.bids().count(), 3);
+    }
+
+    #[test]
+    fn heap_policy_disabled() {
+        let mut book: OrderBook<4, 1> =
+            OrderBook::new(2u8.try_into().unwrap()).with_heap_policy(HeapPolicy::Disabled);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(105, 15.0), tl(120, 25.0)],
+            bids: vec![tl(99, 10.0), tl(95, 20.0), tl(80, 30.0)],
+        });
+
+        println!("{book:#?}");
+
+        assert!(book.asks_heap.is_empty());
+        assert!(book.bids_heap.is_empty());
+        assert_eq!(book.stats().dropped_spills, 4);
+
+        // rebalancing evicts 101 & 99 out of the window
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(90, 1.0)],
+            bids: vec![tl(110, 2.0)],
+        });
+
+        assert!(book.asks_heap.is_empty());
+        assert!(book.bids_heap.is_empty());
+        assert_eq!(book.stats().dropped_spills, 6);
+        assert_eq!(book.asks().count(), 1);
+        assert_eq!(book.bids().count(), 1);
+    }
+
+    #[test]
+    fn size_step_rounding() {
+        let mut book: OrderBook<4, 1> =
+            OrderBook::new(2u8.try_into().unwrap()).with_size_step(0.01);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 0.004), tl(102, 1.236), tl(110, 0.006)],
+            bids: vec![tl(99, 2.5), tl(98, 0.004)],
+        });
+
+        println!("{book:#?}");
+
+        // 0.004 rounds to nothing, the level never lands
+        assert_eq!(book.best_ask().unwrap().price, 1.02);
+        assert!((book.best_ask().unwrap().size - 1.24).abs() < 1e-12);
+        assert!((book.worst_ask().unwrap().size - 0.01).abs() < 1e-12);
+        assert_eq!(book.bid_entries().collect::<Vec<_>>(), vec![(99, 2.5)]);
+
+        // rounding an existing level to zero removes it
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 0.004)],
+        });
+        assert!(book.bid_empty());
+    }
+
+    #[test]
+    fn float_update_matches_tick_update() {
+        let fl = |price, size| FloatLevel { price, size };
+
+        let mut ticks: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = ticks.process_tick_update(&TickUpdate {
+            sequence_id: 7,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
+        });
+
+        // unsorted and a hair off the grid
+        let mut floats: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let outcome = floats.process_float_update(
+            7,
+            &[fl(1.10, 35.0), fl(1.01, 5.0), fl(1.02 + 1e-9, 15.0)],
+            &[fl(0.98, 20.0), fl(0.90, 40.0), fl(0.1 + 0.89, 10.0)],
+        );
+
+        println!("{floats:#?}");
+        assert_eq!(outcome, Ok(UpdateOutcome::Changed));
+        assert_eq!(floats.sequence_id(), ticks.sequence_id());
+        assert!(floats.ask_ticks().eq(ticks.ask_ticks()));
+        assert!(floats.bid_ticks().eq(ticks.bid_ticks()));
+
+        // one bad price rejects the whole update
+        assert_eq!(
+            floats.process_float_update(8, &[fl(1.01, 0.0)], &[fl(f64::NAN, 1.0)]),
+            Err(TickConversionError::NotFinite)
+        );
+        assert_eq!(floats.sequence_id(), 7);
+        assert!(floats.ask_ticks().eq(ticks.ask_ticks()));
+
+        // ticks past u32 convert straight into a u64 book
+        let mut book: OrderBook64<4, 1> = OrderBook64::new(2u8.try_into().unwrap());
+        let outcome = book.process_float_update(
+            1,
+            &[fl(50_000_000.01, 5.0), fl(50_000_000.02, 15.0)],
+            &[fl(49_999_999.99, 10.0)],
+        );
+        assert_eq!(outcome, Ok(UpdateOutcome::Changed));
+        assert_eq!(book.best_ask_tick(), Some(5_000_000_001));
+        assert_eq!(book.best_bid_tick(), Some(4_999_999_999));
+        assert_eq!(book.best_ask().unwrap().price, 50_000_000.01);
+    }
+
+    #[test]
+    fn size_deltas() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        // onto an empty book
+        assert_eq!(book.adjust_ask(101, 5.0), UpdateOutcome::Changed);
+        assert_eq!(book.adjust_bid(99, 10.0), UpdateOutcome::Changed);
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(110, 35.0)],
+            bids: vec![tl(90, 40.0)],
+        });
+
+        // cache
+        let _ = book.adjust_ask(101, 2.5);
+        let _ = book.adjust_bid(99, -4.0);
+        // heap
+        let _ = book.adjust_ask(110, -5.0);
+        let _ = book.adjust_bid(90, 1.0);
+        let _ = book.adjust_ask(120, 1.0);
+
+        println!("{book:#?}");
+
+        assert_eq!(book.sequence_id(), 1);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(101, 7.5), (110, 30.0), (120, 1.0)]
+        );
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(99, 6.0), (90, 41.0)]
+        );
+
+        // subtracting more than is there removes the level
+        let _ = book.adjust_ask(101, -100.0);
+        let _ = book.adjust_bid(90, -41.0);
+        assert_eq!(book.adjust_bid(80, -1.0), UpdateOutcome::Unchanged);
+
+        assert_eq!(book.best_ask_tick(), Some(110));
+        assert_eq!(book.bid_entries().collect::<Vec<_>>(), vec![(99, 6.0)]);
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[test]
+    fn equal_sequence_policies() {
+        let first = TickUpdate {
+            sequence_id: 7,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        };
+        let resend = TickUpdate {
+            sequence_id: 7,
+            timestamp: None,
+            asks: vec![tl(101, 6.0)],
+            bids: vec![tl(99, 11.0)],
+        };
+
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&first);
+        assert_eq!(book.process_tick_update(&resend), UpdateOutcome::Changed);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(101, 6.0), (102, 15.0)]
+        );
+
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap())
+            .with_equal_sequence_policy(EqualSequencePolicy::Ignore);
+        let _ = book.process_tick_update(&first);
+        assert_eq!(book.process_tick_update(&resend), UpdateOutcome::Unchanged);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(101, 5.0), (102, 15.0)]
+        );
+
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap())
+            .with_equal_sequence_policy(EqualSequencePolicy::Replace);
+        let _ = book.process_tick_update(&first);
+        assert_eq!(book.process_tick_update(&resend), UpdateOutcome::Changed);
+
+        println!("{book:#?}");
+
+        assert_eq!(book.ask_entries().collect::<Vec<_>>(), vec![(101, 6.0)]);
+        assert_eq!(book.bid_entries().collect::<Vec<_>>(), vec![(99, 11.0)]);
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[test]
+    fn heap_policy_drop_beyond() {
+        let mut book: OrderBook<4, 1> =
+            OrderBook::new(2u8.try_into().unwrap()).with_heap_policy(HeapPolicy::DropBeyond(5));
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(105, 15.0), tl(120, 25.0)],
+            bids: vec![tl(99, 10.0), tl(95, 20.0), tl(80, 30.0)],
+        });
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_heap.len(), 1);
+        assert_eq!(book.asks_heap.get(&105).map(|(size, _)| *size), Some(15.0));
+        assert_eq!(book.bids_heap.len(), 1);
+        assert_eq!(book.bids_heap.get(&95).map(|(size, _)| *size), Some(20.0));
+
+        // rebalancing evicts 101 & 99 out of the window, now too far to keep
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(90, 1.0)],
+            bids: vec![tl(110, 2.0)],
+        });
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        assert!(!book.asks_heap.contains_key(&101));
+        assert!(!book.bids_heap.contains_key(&99));
+        // levels already in the heap are left alone
+        assert_eq!(book.asks_heap.get(&105).map(|(size, _)| *size), Some(15.0));
+        assert_eq!(book.bids_heap.get(&95).map(|(size, _)| *size), Some(20.0));
+        assert_eq!(book.asks().count(), 2);
+        assert_eq!(book.bids().count(), 2);
+    }
+
+    #[test]
+    fn entries() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(103, 25.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0), tl(90, 40.0)],
+        });
+
+        let ask_entries: Vec<_> = book.ask_entries().collect();
+        let bid_entries: Vec<_> = book.bid_entries().collect();
+
+        assert_eq!(
+            ask_entries,
+            vec![(101, 5.0), (102, 15.0), (103, 25.0), (110, 35.0)]
+        );
+        assert_eq!(
+            bid_entries,
+            vec![(99, 10.0), (98, 20.0), (97, 30.0), (90, 40.0)]
+        );
+
+        let ask_ticks: Vec<_> = book.ask_ticks().map(|l| (l.tick, l.size)).collect();
+        let bid_ticks: Vec<_> = book.bid_ticks().map(|l| (l.tick, l.size)).collect();
+
+        assert_eq!(ask_entries, ask_ticks);
+        assert_eq!(bid_entries, bid_ticks);
+
+        let ask_entries_rev: Vec<_> = book.ask_entries().rev().collect();
+        assert_eq!(
+            ask_entries_rev,
+            ask_entries.iter().rev().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn asks_refill_near_u32_max() {
+        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(u32::MAX - 4, 5.0), tl(u32::MAX, 7.0)],
+            bids: vec![],
+        });
+
+        assert_eq!(book.asks_0_tick, u32::MAX - 5);
+        assert_eq!(
+            book.asks_heap.get(&u32::MAX).map(|(size, _)| *size),
+            Some(7.0)
+        );
+
+        // shift of 3 puts the last refill slot past u32::MAX
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(u32::MAX - 4, 0.0), tl(u32::MAX - 1, 3.0)],
+            bids: vec![],
+        });
+
+        println!("{book:#?}");
+
+        assert_eq!(book.asks_0_tick, u32::MAX - 2);
+        assert_eq!(book.best_ask_i, 1);
+        assert_eq!(book.asks[1], 3.0); // tick u32::MAX - 1
+        assert_eq!(book.asks[2], 7.0); // tick u32::MAX
+        assert_eq!(book.asks[3], 0.0);
+        assert_eq!(book.asks[4], 0.0);
+        assert_eq!(book.asks_heap.len(), 0);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(u32::MAX - 1, 3.0), (u32::MAX, 7.0)]
+        );
+    }
+
+    #[test]
+    fn bids_refill_near_zero() {
+        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(4, 5.0), tl(0, 7.0)],
+        });
+
+        assert_eq!(book.bids_0_tick, 5);
+        assert_eq!(book.bids_heap.get(&0).map(|(size, _)| *size), Some(7.0));
+
+        // shift of 3 puts the last refill slot below tick 0
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(4, 0.0), tl(1, 3.0)],
+        });
+
+        println!("{book:#?}");
+
+        assert_eq!(book.bids_0_tick, 2);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.bids[1], 3.0); // tick 1
+        assert_eq!(book.bids[2], 7.0); // tick 0
+        assert_eq!(book.bids[3], 0.0);
+        assert_eq!(book.bids[4], 0.0);
+        assert_eq!(book.bids_heap.len(), 0);
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(1, 3.0), (0, 7.0)]
+        );
+    }
+
+    #[test]
+    fn extend() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let updates = (0..6u32).map(|i| TickUpdate {
+            sequence_id: i as u64,
+            timestamp: None,
+            asks: vec![tl(101 + i, 1.0 + i as f64)],
+            bids: vec![tl(99 - i, 1.0 + i as f64)],
+        });
+
+        book.extend(updates.filter(|update| update.sequence_id % 2 == 0));
+        println!("{book:#?}");
+
+        assert_eq!(book.sequence_id(), 4);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(101, 1.0), (103, 3.0), (105, 5.0)]
+        );
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(99, 1.0), (97, 3.0), (95, 5.0)]
+        );
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[test]
+    fn from_updates() {
+        let updates = vec![
+            TickUpdate {
+                sequence_id: 0,
+                timestamp: None,
+                asks: vec![tl(101, 5.0), tl(102, 15.0)],
+                bids: vec![tl(99, 10.0), tl(98, 20.0)],
+            },
+            TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![tl(101, 0.0)],
+                bids: vec![tl(100, 3.0)],
+            },
+            TickUpdate {
+                sequence_id: 2,
+                timestamp: Some(9),
+                asks: vec![tl(102, 12.0)],
+                bids: vec![tl(100, 0.0)],
+            },
+        ];
+
+        let book: OrderBook<4, 1> = OrderBook::from_updates(2u8.try_into().unwrap(), updates);
+
+        println!("{book:#?}");
+        assert_eq!(book.sequence_id(), 2);
+        assert_eq!(book.last_timestamp(), Some(9));
+        assert_eq!(book.best_ask_tick(), Some(102));
+        assert_eq!(book.best_ask().unwrap().size, 12.0);
+        assert_eq!(book.best_bid_tick(), Some(99));
+    }
+
+    #[test]
+    fn from_levels_matches_incremental() {
+        let snapshots = [
+            TickUpdate {
+                sequence_id: 7,
+                timestamp: None,
+                asks: vec![tl(101, 5.0), tl(102, 15.0), tl(103, 25.0), tl(110, 35.0)],
+                bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0), tl(90, 40.0)],
+            },
+            TickUpdate {
+                sequence_id: 8,
+                timestamp: None,
+                asks: vec![tl(2, 5.0), tl(40, 15.0)],
+                bids: vec![tl(1, 10.0), tl(0, 20.0)],
+            },
+            TickUpdate {
+                sequence_id: 9,
+                timestamp: None,
+                asks: vec![tl(101, 5.0)],
+                bids: vec![],
+            },
+            TickUpdate {
+                sequence_id: 10,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![],
+            },
+        ];
+
+        for snapshot in &snapshots {
+            let mut incremental: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+            let _ = incremental.process_tick_update(snapshot);
+
+            let bulk: OrderBook<4, 1> = OrderBook::from_levels(
+                2u8.try_into().unwrap(),
+                snapshot.sequence_id,
+                &snapshot.asks,
+                &snapshot.bids,
+            );
+
+            println!("{bulk:#?}");
+            println!("{bulk}");
+
+            assert_eq!(format!("{incremental:?}"), format!("{bulk:?}"));
+        }
+    }
+
+    #[test]
+    fn last_timestamp() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.last_timestamp(), None);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: Some(1_700_000_000_000_000_000),
+            asks: vec![tl(101, 5.0)],
+            bids: vec![tl(99, 10.0)],
+        });
+        assert_eq!(book.last_timestamp(), Some(1_700_000_000_000_000_000));
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            asks: vec![tl(102, 5.0)],
+            ..Default::default()
+        });
+        assert_eq!(book.sequence_id(), 1);
+        assert_eq!(book.last_timestamp(), Some(1_700_000_000_000_000_000));
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: Some(1_700_000_000_000_000_001),
+            ..Default::default()
+        });
+        assert_eq!(book.last_timestamp(), Some(1_700_000_000_000_000_001));
+    }
+
+    #[test]
+    fn negative_ask_size_rejected() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0)],
+        });
+
+        let result = book.try_process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, -3.0)],
+            bids: vec![tl(98, 1.0)],
+        });
+
+        assert_eq!(
+            result,
+            Err(UpdateError::InvalidSize {
+                side: Side::Ask,
+                tick: 102,
+                size: -3.0
+            })
+        );
+        assert_eq!(book.sequence_id(), 0);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(101, 5.0), (102, 15.0)]
+        );
+        assert_eq!(book.bid_entries().collect::<Vec<_>>(), vec![(99, 10.0)]);
+    }
+
+    #[test]
+    fn negative_bid_size_rejected() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        });
+
+        let result = book.try_process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, -10.0)],
+        });
+
+        assert_eq!(
+            result,
+            Err(UpdateError::InvalidSize {
+                side: Side::Bid,
+                tick: 99,
+                size: -10.0
+            })
+        );
+        assert_eq!(book.best_bid().unwrap().size, 10.0);
+
+        assert!(matches!(
+            book.try_process_tick_update(&TickUpdate {
+                sequence_id: 2,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![tl(99, f64::NAN)],
+            }),
+            Err(UpdateError::InvalidSize {
+                side: Side::Bid,
+                tick: 99,
+                ..
+            })
+        ));
+
+        assert_eq!(
+            book.try_process_tick_update(&TickUpdate {
+                sequence_id: 3,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![tl(99, 0.0)],
+            }),
+            Ok(UpdateOutcome::Changed)
+        );
+        assert_eq!(book.sequence_id(), 3);
+        assert_eq!(book.best_bid().unwrap().size, 20.0);
+    }
+
+    #[cfg(feature = "internals")]
+    #[test]
+    fn raw_cache_matches_levels() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(103, 25.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(97, 30.0), tl(90, 40.0)],
+        });
+
+        let decimals = book.tick_decimals;
+
+        let (asks, asks_0_tick) = book.ask_cache();
+        let cached_asks: Vec<_> = asks
+            .iter()
+            .enumerate()
+            .filter(|(_, sz)| **sz >= EPSILON)
+            .map(|(i, sz)| (decimals.fast_tick_to_f64(asks_0_tick + i as u32), *sz))
+            .collect();
+        let expected: Vec<_> = book.asks().map(|l| (l.price, l.size)).take(2).collect();
+        assert_eq!(cached_asks, expected);
+
+        let (bids, bids_0_tick) = book.bid_cache();
+        let cached_bids: Vec<_> = bids
+            .iter()
+            .enumerate()
+            .filter(|(_, sz)| **sz >= EPSILON)
+            .map(|(i, sz)| (decimals.fast_tick_to_f64(bids_0_tick - i as u32), *sz))
+            .collect();
+        let expected: Vec<_> = book.bids().map(|l| (l.price, l.size)).take(2).collect();
+        assert_eq!(cached_bids, expected);
+    }
+
+    #[test]
+    fn u64_ticks_beyond_u32_max() {
+        let mut book: OrderBook64<4, 1> = OrderBook64::new(2u8.try_into().unwrap());
+
+        let mid = u32::MAX as u64 * 4;
+        let tl64 = |tick: u64, size: f64| TickLevel { tick, size };
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![
+                tl64(mid + 1, 5.0),
+                tl64(mid + 2, 15.0),
+                tl64(mid + 10, 25.0),
+            ],
+            bids: vec![
+                tl64(mid - 1, 10.0),
+                tl64(mid - 2, 20.0),
+                tl64(mid - 10, 30.0),
+            ],
+        });
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        assert_eq!(book.asks_0_tick, mid);
+        assert_eq!(book.bids_0_tick, mid);
+        assert_eq!(
+            book.asks_heap.get(&(mid + 10)).map(|(size, _)| *size),
+            Some(25.0)
+        );
+        assert_eq!(
+            book.bids_heap.get(&(mid - 10)).map(|(size, _)| *size),
+            Some(30.0)
+        );
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(mid + 1, 5.0), (mid + 2, 15.0), (mid + 10, 25.0)]
+        );
+
+        let best_ask = book.best_ask().unwrap();
+        assert_eq!(best_ask.price, (mid + 1) as f64 * 0.01);
+        assert_eq!(best_ask.size, 5.0);
+        assert_eq!(book.best_bid().unwrap().size, 10.0);
+
+        // walk the best ask down past the cache window to force a rebalance
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl64(mid - 20, 1.0)],
+            bids: vec![tl64(mid - 30, 0.0)],
+        });
+
+        assert_eq!(book.asks_0_tick, mid - 21);
+        assert_eq!(book.best_ask().unwrap().size, 1.0);
+        assert_eq!(book.ask_entries().count(), 4);
+    }
+
+    #[test]
+    fn retain_levels() {
+        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![
+                tl(101, 5.0),
+                tl(102, 15.0),
+                tl(103, 2.0),
+                tl(110, 35.0),
+                tl(111, 1.0),
+            ],
+            bids: vec![
+                tl(99, 1.0),
+                tl(98, 2.0),
+                tl(97, 30.0),
+                tl(90, 3.0),
+                tl(89, 40.0),
+            ],
+        });
+
+        book.retain_asks(|_, size| size >= 10.0);
+        book.retain_bids(|_, size| size >= 10.0);
+
+        println!("{book:#?}");
+        println!("{book}");
+
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(102, 15.0), (110, 35.0)]
+        );
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(97, 30.0), (89, 40.0)]
+        );
+        assert_eq!(book.best_ask().unwrap().price, 1.02);
+        assert_eq!(book.best_bid().unwrap().price, 0.97);
+        assert_eq!(book.asks_0_tick, 100);
+        assert_eq!(book.best_ask_i, 2);
+        // best moved past the padding so the bid window recentered
+        assert_eq!(book.bids_0_tick, 98);
+        assert_eq!(book.best_bid_i, 1);
+        assert_eq!(book.asks_heap.len(), 1);
+        assert_eq!(book.bids_heap.len(), 1);
+
+        book.retain_asks(|tick, _| tick > 200);
+        assert!(book.best_ask().is_none());
+        assert_eq!(book.ask_entries().count(), 0);
+    }
+
+    #[test]
+    fn truncate_depth() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(103, 15.0), tl(104, 1.0), tl(150, 35.0)],
+            bids: vec![tl(99, 10.0), tl(97, 20.0), tl(96, 1.0), tl(50, 40.0)],
+        });
+        assert!(!book.asks_heap.is_empty());
+        assert!(!book.bids_heap.is_empty());
+
+        book.truncate_depth(2);
+        println!("{book:#?}");
+
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(101, 5.0), (103, 15.0)]
+        );
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(99, 10.0), (97, 20.0)]
+        );
+        assert!(book.asks_heap.is_empty());
+        assert!(book.bids_heap.is_empty());
+        assert_eq!(book.validate(), Ok(()));
+
+        // deeper than the book is a no-op
+        book.truncate_depth(5);
+        assert_eq!(book.total_levels(), 4);
+
+        book.truncate_depth(0);
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn outcome_gates_downstream_work() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let mut recomputed = 0;
+
+        for (seq, size) in [5.0, 5.0, 6.0, 6.0 + EPSILON / 2.0, 0.0]
+            .into_iter()
+            .enumerate()
+        {
+            let outcome = book.process_tick_update(&TickUpdate {
+                sequence_id: seq as u64,
+                timestamp: None,
+                asks: vec![tl(101, size)],
+                bids: vec![tl(99, 10.0)],
+            });
+            if outcome == UpdateOutcome::Changed {
+                recomputed += 1;
+            }
+        }
+
+        println!("{book:#?}");
+        // first fill, resize and removal
+        assert_eq!(recomputed, 3);
+        assert_eq!(book.sequence_id(), 4);
+    }
+
+    #[test]
+    fn evict_stale() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let update = |book: &mut OrderBook<4, 1>, seq, asks, bids| {
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: seq,
+                timestamp: None,
+                asks,
+                bids,
+            });
+        };
 
-        let i = (ask.tick - self.asks_0_tick) as usize;
+        update(
+            &mut book,
+            1,
+            vec![tl(101, 5.0), tl(150, 1.0)],
+            vec![tl(99, 5.0), tl(50, 1.0)],
+        );
+        update(&mut book, 2, vec![tl(160, 2.0)], vec![tl(40, 2.0)]);
+        // refreshed, same size
+        update(&mut book, 3, vec![tl(150, 1.0)], vec![]);
+        println!("{book:#?}");
 
-        // cache
-        if i < CACHE_SLOTS {
-            self.asks[i] = ask.size;
-        }
-        // heap escape - 0 size
-        else if ask.size < EPSILON {
-            self.asks_heap.remove(&ask.tick);
-        }
-        // heap escape - upsert
-        else {
-            self.asks_heap
-                .entry(ask.tick)
-                .and_modify(|sz| *sz = ask.size)
-                .or_insert(ask.size);
-        }
+        assert_eq!(book.evict_stale(3), 3);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(101, 5.0), (150, 1.0)]
+        );
+        assert_eq!(book.bid_entries().collect::<Vec<_>>(), vec![(99, 5.0)]);
+        assert_eq!(book.validate(), Ok(()));
+
+        // cache levels are never stale
+        assert_eq!(book.evict_stale(10), 1);
+        assert_eq!(book.total_levels(), 2);
+        assert_eq!(book.best_ask_tick(), Some(101));
+
+        // written at 1, stamped 11 when the window moved up past it
+        update(
+            &mut book,
+            11,
+            vec![tl(101, 0.0), tl(130, 1.0)],
+            vec![tl(120, 1.0)],
+        );
+        assert_eq!(book.bid_heap_bounds(), Some((99, 99)));
+        assert_eq!(book.evict_stale(11), 0);
     }
 
-    /// invariant: highest_tick > self.bids_0_tick
-    ///
-    /// enforces invariant: highest_tick <= bids_0_tick
-    #[inline]
-    fn rebalance_bids_higher(&mut self, highest_tick: u32) {
-        debug_assert!(highest_tick > self.bids_0_tick);
+    #[test]
+    fn repeated_update_is_unchanged() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let update = TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
+        };
 
-        let new_bids_0_tick = highest_tick + CACHE_EMPTY_SLOTS as u32;
-        let shift = (new_bids_0_tick - self.bids_0_tick) as usize;
+        assert_eq!(book.process_tick_update(&update), UpdateOutcome::Changed);
+        assert_eq!(book.process_tick_update(&update), UpdateOutcome::Unchanged);
 
-        // rebuild cache
-        let i_eviction_start: usize = if shift >= CACHE_SLOTS {
-            0
-        } else {
-            CACHE_SLOTS - shift
+        // removing levels that don't exist is a no-op too
+        let update = TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(103, 0.0), tl(120, 0.0)],
+            bids: vec![tl(97, 0.0), tl(80, 0.0)],
         };
+        assert_eq!(book.process_tick_update(&update), UpdateOutcome::Unchanged);
+        assert_eq!(book.sequence_id(), 1);
 
-        for i in i_eviction_start..CACHE_SLOTS {
-            // TODO: can replace with next initialized tick offsets
-            if self.bids[i] > EPSILON {
-                let tick = self.bids_0_tick - i as u32;
-                self.bids_heap
-                    .entry(tick)
-                    .and_modify(|sz| *sz = self.bids[i])
-                    .or_insert(self.bids[i]);
+        // heap level resize
+        let update = TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(110, 36.0)],
+            bids: vec![],
+        };
+        assert_eq!(book.process_tick_update(&update), UpdateOutcome::Changed);
 
-                self.bids[i] = 0.0
-            }
-        }
+        // cache level removal
+        let update = TickUpdate {
+            sequence_id: 3,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 0.0)],
+        };
+        assert_eq!(book.process_tick_update(&update), UpdateOutcome::Changed);
+        assert_eq!(book.process_tick_update(&update), UpdateOutcome::Unchanged);
+    }
 
-        for i in (0..i_eviction_start).rev() {
-            self.bids[i + shift] = self.bids[i];
-            self.bids[i] = 0.0;
+    #[test]
+    fn asks_jump_across_whole_window() {
+        // shift == CACHE_SLOTS - 1, CACHE_SLOTS, CACHE_SLOTS + 1
+        for shift in [4, 5, 6] {
+            let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 0,
+                timestamp: None,
+                asks: vec![tl(101, 1.0), tl(102, 2.0), tl(103, 3.0), tl(104, 4.0)],
+                bids: vec![],
+            });
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![tl(100, 10.0)],
+                bids: vec![],
+            });
+            // every slot of the window 100..=104 is populated
+            assert_eq!(book.asks_0_tick, 100);
+            assert!(book.asks.iter().all(|sz| *sz > EPSILON));
+
+            let lowest = 101 - shift;
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 2,
+                timestamp: None,
+                asks: vec![tl(lowest, 7.0)],
+                bids: vec![],
+            });
+
+            println!("shift {shift}:\n{book:#?}");
+
+            assert_eq!(book.asks_0_tick, 100 - shift);
+            assert_eq!(book.best_ask().unwrap().size, 7.0);
+            assert_eq!(
+                book.ask_entries().collect::<Vec<_>>(),
+                vec![
+                    (lowest, 7.0),
+                    (100, 10.0),
+                    (101, 1.0),
+                    (102, 2.0),
+                    (103, 3.0),
+                    (104, 4.0)
+                ]
+            );
+
+            // the heap holds exactly the evicted levels
+            let evicted: Vec<_> = (100..=104).filter(|t| *t > 104 - shift).collect();
+            assert_eq!(book.asks_heap.keys().copied().collect::<Vec<_>>(), evicted);
         }
+    }
 
-        self.bids_0_tick = new_bids_0_tick;
+    #[test]
+    fn bids_jump_across_whole_window() {
+        // shift == CACHE_SLOTS - 1, CACHE_SLOTS, CACHE_SLOTS + 1
+        for shift in [4, 5, 6] {
+            let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 0,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![tl(99, 1.0), tl(98, 2.0), tl(97, 3.0), tl(96, 4.0)],
+            });
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![tl(100, 10.0)],
+            });
+            // every slot of the window 100..=96 is populated
+            assert_eq!(book.bids_0_tick, 100);
+            assert!(book.bids.iter().all(|sz| *sz > EPSILON));
+
+            let highest = 99 + shift;
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 2,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![tl(highest, 7.0)],
+            });
+
+            println!("shift {shift}:\n{book:#?}");
+
+            assert_eq!(book.bids_0_tick, 100 + shift);
+            assert_eq!(book.best_bid().unwrap().size, 7.0);
+            assert_eq!(
+                book.bid_entries().collect::<Vec<_>>(),
+                vec![
+                    (highest, 7.0),
+                    (100, 10.0),
+                    (99, 1.0),
+                    (98, 2.0),
+                    (97, 3.0),
+                    (96, 4.0)
+                ]
+            );
+
+            // the heap holds exactly the evicted levels
+            let evicted: Vec<_> = (96..=100).filter(|t| *t < 96 + shift).collect();
+            assert_eq!(book.bids_heap.keys().copied().collect::<Vec<_>>(), evicted);
+        }
     }
 
-    /// invariant: lowest_tick < self.asks_0_tick
-    ///
-    /// enforces invariant: lowest_tick >= asks_0_tick
-    #[inline]
-    fn rebalance_asks_lower(&mut self, lowest_tick: u32) {
-        debug_assert!(lowest_tick < self.asks_0_tick);
+    #[test]
+    fn evictions_reported() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(103, 25.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
+        });
 
-        let new_asks_0_tick = lowest_tick.saturating_sub(CACHE_EMPTY_SLOTS as u32);
-        let shift = (self.asks_0_tick - new_asks_0_tick) as usize;
+        // window shifts by 2, pushing the two worst levels of each side out
+        let mut evicted = Vec::new();
+        let _ = book.process_tick_update_with_evictions(
+            &TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![tl(99, 1.0)],
+                bids: vec![tl(101, 2.0)],
+            },
+            &mut evicted,
+        );
 
-        // rebuild cache
-        let i_eviction_start: usize = if shift >= CACHE_SLOTS {
-            0
-        } else {
-            CACHE_SLOTS - shift
-        };
+        println!("{book:#?}");
 
-        for i in i_eviction_start..CACHE_SLOTS {
-            // TODO: can replace with next initialized tick offsets
-            if self.asks[i] > EPSILON {
-                let tick = i as u32 + self.asks_0_tick;
-                self.asks_heap
-                    .entry(tick)
-                    .and_modify(|sz| *sz = self.asks[i])
-                    .or_insert(self.asks[i]);
+        assert_eq!(
+            evicted,
+            vec![
+                (Side::Ask, 102),
+                (Side::Ask, 103),
+                (Side::Bid, 98),
+                (Side::Bid, 97)
+            ]
+        );
+        assert_eq!(
+            book.asks_heap.keys().copied().collect::<Vec<_>>(),
+            vec![102, 103]
+        );
+        assert_eq!(
+            book.bids_heap.keys().copied().collect::<Vec<_>>(),
+            vec![97, 98]
+        );
+
+        // updates within the window evict nothing
+        evicted.clear();
+        let _ = book.process_tick_update_with_evictions(
+            &TickUpdate {
+                sequence_id: 2,
+                timestamp: None,
+                asks: vec![tl(100, 1.0)],
+                bids: vec![tl(100, 2.0)],
+            },
+            &mut evicted,
+        );
+        assert!(evicted.is_empty());
+    }
 
-                self.asks[i] = 0.0
-            }
-        }
+    #[test]
+    fn one_sided_book() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        for i in (0..i_eviction_start).rev() {
-            self.asks[i + shift] = self.asks[i];
-            self.asks[i] = 0.0;
-        }
+        assert!(book.is_empty());
+        assert!(book.bid_empty() && book.ask_empty());
+        assert!(!book.has_both_sides());
 
-        self.asks_0_tick = new_asks_0_tick;
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 10.0), tl(80, 20.0)],
+        });
+
+        assert!(!book.is_empty());
+        assert!(!book.bid_empty());
+        assert!(book.ask_empty());
+        assert!(!book.has_both_sides());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 5.0)],
+            bids: vec![],
+        });
+        assert!(book.has_both_sides());
+
+        // clearing the cache leaves the heap level as the best bid
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(101, 0.0)],
+            bids: vec![tl(99, 0.0)],
+        });
+        assert!(!book.bid_empty());
+        assert!(book.ask_empty());
+        assert!(!book.has_both_sides());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[cfg(feature = "price-memo")]
+    #[test]
+    fn memoized_prices_match_conversion() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-    fn tl(tick: u32, size: f64) -> TickLevel {
-        TickLevel { tick, size }
+        let direct = |book: &OrderBook<4, 1>| {
+            (
+                book.ask_entries()
+                    .map(|(tick, size)| (book.tick_decimals.tick_to_f64(tick), size))
+                    .collect::<Vec<_>>(),
+                book.bid_entries()
+                    .map(|(tick, size)| (book.tick_decimals.tick_to_f64(tick), size))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let memoized = |book: &OrderBook<4, 1>| {
+            (
+                book.asks().map(|l| (l.price, l.size)).collect::<Vec<_>>(),
+                book.bids().map(|l| (l.price, l.size)).collect::<Vec<_>>(),
+            )
+        };
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
+        });
+        assert_eq!(memoized(&book), direct(&book));
+        // second read hits the memo
+        assert_eq!(memoized(&book), direct(&book));
+
+        // window moves, memo is rebuilt for the new anchor
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(97, 1.0)],
+            bids: vec![tl(103, 2.0)],
+        });
+        assert_eq!(memoized(&book), direct(&book));
+        assert_eq!(book.asks().rev().count(), 4);
     }
 
     #[test]
-    fn best_ask() {
-        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+    fn best_ticks() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.best_ask_tick(), None);
+        assert_eq!(book.best_bid_tick(), None);
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![tl(2, 5.0)],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(110, 35.0)],
             bids: vec![],
         });
+        assert_eq!(book.best_ask_tick(), Some(101));
+        assert_eq!(book.best_bid_tick(), None);
 
-        let best_ask = book.best_ask();
+        // best ask refilled from the heap
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        });
 
-        assert_eq!(best_ask.price, 0.02);
-        assert_eq!(best_ask.size, 5.0);
+        println!("{book:#?}");
+
+        assert_eq!(book.best_ask_tick(), Some(110));
+        assert_eq!(book.best_bid_tick(), Some(99));
+        assert_eq!(
+            book.best_ask().unwrap().price,
+            book.tick_decimals.tick_to_f64(110u32)
+        );
     }
 
     #[test]
-    fn best_bid() {
-        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+    fn gap_ticks() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![],
-            bids: vec![tl(1, 10.0)],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0)],
         });
+        assert_eq!(book.ask_gap_ticks(), Some(1));
+        assert_eq!(book.bid_gap_ticks(), None);
 
-        let best_bid = book.best_bid();
+        // next level out in the heap
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(102, 0.0), tl(110, 35.0)],
+            bids: vec![tl(90, 40.0)],
+        });
 
-        assert_eq!(best_bid.price, 0.01);
-        assert_eq!(best_bid.size, 10.0);
+        println!("{book:#?}");
+
+        assert_eq!(book.ask_gap_ticks(), Some(9));
+        assert_eq!(book.bid_gap_ticks(), Some(9));
     }
 
     #[test]
-    fn init() {
-        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+    fn heap_bounds() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(103, 25.0)],
-            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(90, 40.0)],
+        });
+        assert_eq!(book.ask_heap_bounds(), None);
+        assert_eq!(book.bid_heap_bounds(), Some((90, 90)));
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(110, 35.0), tl(115, 2.0), tl(120, 1.0)],
+            bids: vec![tl(85, 2.0), tl(80, 1.0)],
         });
 
         println!("{book:#?}");
-        println!("{book}");
 
-        assert_eq!(book.sequence_id, 0);
-        assert_eq!(book.asks_0_tick, 100);
-        assert_eq!(book.bids_0_tick, 100);
-        assert_eq!(book.best_ask_i, 1);
-        assert_eq!(book.best_bid_i, 1);
-        assert_eq!(book.asks[0], 0.0);
-        assert_eq!(book.asks[1], 5.0);
-        assert_eq!(book.asks[2], 15.0);
-        assert_eq!(book.bids[0], 0.0);
-        assert_eq!(book.bids[1], 10.0);
-        assert_eq!(book.bids[2], 20.0);
-        assert_eq!(book.asks_heap.len(), 1);
-        assert_eq!(book.asks_heap.get(&103), Some(&25.0));
-        assert_eq!(book.bids_heap.len(), 1);
-        assert_eq!(book.bids_heap.get(&97), Some(&30.0));
+        assert_eq!(book.ask_heap_bounds(), Some((110, 120)));
+        assert_eq!(book.bid_heap_bounds(), Some((80, 90)));
+        assert_eq!(
+            book.worst_ask().unwrap().price,
+            book.tick_decimals.tick_to_f64(120u32)
+        );
     }
 
     #[test]
-    fn update_order_book_with_level_removal_and_addition() {
-        let mut book: OrderBook<3, 1> = OrderBook::new(2u8.try_into().unwrap());
+    fn size_extremes() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.largest_ask(), None);
+        assert_eq!(book.smallest_bid(), None);
 
-        let init = TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![tl(101, 5.0)],
-            bids: vec![tl(99, 10.0)],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 0.5), tl(110, 350.0), tl(111, 0.01)],
+            bids: vec![tl(99, 10.0), tl(98, 10.0), tl(90, 400.0), tl(89, 0.02)],
+        });
+
+        println!("{book:#?}");
+
+        // both extremes live in the heap
+        let level = |price, size| Some(FloatLevel { price, size });
+        assert_eq!(book.largest_ask(), level(1.10, 350.0));
+        assert_eq!(book.smallest_ask(), level(1.11, 0.01));
+        assert_eq!(book.largest_bid(), level(0.90, 400.0));
+        assert_eq!(book.smallest_bid(), level(0.89, 0.02));
+
+        // ties go to the level nearest the best
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(90, 0.0), tl(89, 0.0)],
+        });
+        assert_eq!(book.largest_bid(), level(0.99, 10.0));
+    }
+
+    #[test]
+    fn best_or_default() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let default = FloatLevel {
+            price: f64::NAN,
+            size: 0.0,
         };
 
-        book.process_tick_update(&init);
+        assert!(book.best_bid_or(default).price.is_nan());
+        assert!(book.best_ask_or(default).price.is_nan());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0)],
+            bids: vec![],
+        });
+        assert_eq!(book.best_ask_or(default), book.best_ask().unwrap());
+        assert_eq!(book.best_bid_or(default).size, 0.0);
+    }
+
+    #[test]
+    fn grids() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(103, 15.0), tl(106, 35.0)],
+            bids: vec![tl(99, 10.0), tl(96, 40.0)],
+        });
 
         println!("{book:#?}");
-        println!("{book}");
 
-        assert_eq!(book.asks_0_tick, 100);
-        assert_eq!(book.asks[1], 5.0); // tick 101
-        assert_eq!(book.asks_heap.len(), 0);
+        let sizes = |grid: &mut dyn Iterator<Item = FloatLevel>| -> Vec<f64> {
+            grid.map(|level| level.size).collect()
+        };
+        // runs through the cache into the heap and past the last level
+        assert_eq!(
+            sizes(&mut book.ask_grid(100, 107)),
+            vec![0.0, 5.0, 0.0, 15.0, 0.0, 0.0, 35.0, 0.0]
+        );
+        assert_eq!(
+            sizes(&mut book.bid_grid(95, 99)),
+            vec![0.0, 40.0, 0.0, 0.0, 10.0]
+        );
+
+        let grid: Vec<_> = book.ask_grid(102, 103).collect();
+        assert_eq!(grid[0].price, book.tick_decimals.tick_to_f64(102u32));
+        assert_eq!(grid[1].size, 15.0);
+
+        assert_eq!(book.ask_grid(103, 102).count(), 0);
+        assert_eq!(sizes(&mut book.ask_grid(103, 103)), vec![15.0]);
+    }
 
-        assert_eq!(book.bids_0_tick, 100);
-        assert_eq!(book.bids[1], 10.0); // tick 99
-        assert_eq!(book.bids_heap.len(), 0);
+    #[test]
+    fn level_arrays() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        let update = TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
+        });
+
+        let (ask_prices, ask_sizes) = book.ask_arrays(10);
+        let (bid_prices, bid_sizes) = book.bid_arrays(2);
+
+        assert_eq!(ask_prices.len(), ask_sizes.len());
+        assert_eq!(ask_prices.len(), 3);
+        assert!(ask_prices.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(ask_sizes, vec![5.0, 15.0, 35.0]);
+
+        assert_eq!(bid_prices.len(), bid_sizes.len());
+        assert_eq!(bid_prices.len(), 2);
+        assert!(bid_prices.windows(2).all(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn worst_levels() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        assert!(book.worst_ask().is_none());
+        assert!(book.worst_bid().is_none());
+
+        // all inside the window
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(97, 20.0)],
+        });
+
+        assert!(book.asks_heap.is_empty() && book.bids_heap.is_empty());
+        assert_eq!(book.worst_ask().unwrap().size, 15.0);
+        assert_eq!(
+            book.worst_ask().unwrap().price,
+            book.asks().next_back().unwrap().price
+        );
+        assert_eq!(book.worst_bid().unwrap().size, 20.0);
+        assert_eq!(
+            book.worst_bid().unwrap().price,
+            book.bids().next_back().unwrap().price
+        );
+
+        // spilled to the heap
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 1,
-            asks: vec![tl(101, 0.0), tl(102, 15.0)],
-            bids: vec![tl(99, 0.0), tl(98, 20.0)],
-        };
+            timestamp: None,
+            asks: vec![tl(110, 35.0), tl(120, 45.0)],
+            bids: vec![tl(90, 40.0), tl(80, 50.0)],
+        });
+
+        assert_eq!(book.worst_ask().unwrap().size, 45.0);
+        assert_eq!(
+            book.worst_ask().unwrap().price,
+            book.asks().next_back().unwrap().price
+        );
+        assert_eq!(book.worst_bid().unwrap().size, 50.0);
+        assert_eq!(
+            book.worst_bid().unwrap().price,
+            book.bids().next_back().unwrap().price
+        );
+    }
+
+    /// `Global`, counting allocations
+    #[cfg(feature = "allocator_api")]
+    #[derive(Clone, Default)]
+    struct Tracking(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    #[cfg(feature = "allocator_api")]
+    impl Tracking {
+        fn count(&self) -> usize {
+            self.0.load(std::sync::atomic::Ordering::Relaxed)
+        }
+    }
+
+    #[cfg(feature = "allocator_api")]
+    unsafe impl std::alloc::Allocator for Tracking {
+        fn allocate(
+            &self,
+            layout: std::alloc::Layout,
+        ) -> Result<std::ptr::NonNull<[u8]>, std::alloc::AllocError> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            std::alloc::Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: std::ptr::NonNull<u8>, layout: std::alloc::Layout) {
+            unsafe { std::alloc::Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[cfg(feature = "allocator_api")]
+    #[test]
+    fn heaps_allocate_through_custom_allocator() {
+        let alloc = Tracking::default();
+        let mut book: OrderBook<4, 1, u32, Tracking> =
+            OrderBook::new_in(2u8.try_into().unwrap(), alloc.clone());
+
+        // cache only
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0)],
+            bids: vec![tl(99, 10.0)],
+        });
+        assert_eq!(alloc.count(), 0);
+
+        // spill
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(110, 35.0)],
+            bids: vec![tl(90, 40.0)],
+        });
+        assert!(alloc.count() >= 2);
+        assert_eq!(book.asks().count(), 2);
+        assert_eq!(book.bids().count(), 2);
+    }
+
+    #[cfg(feature = "allocator_api")]
+    #[test]
+    fn heap_spill_burst_from_arena() {
+        use crate::heap_alloc::{Arena, HeapAlloc};
+
+        fn spill_burst<A: HeapAlloc>(book: &mut OrderBook<4, 1, u32, A>) {
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 0,
+                timestamp: None,
+                asks: vec![tl(1001, 5.0)],
+                bids: vec![tl(999, 10.0)],
+            });
+            // burst of levels spilling into both heaps
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: (1010..1510).map(|t| tl(t, 1.0)).collect(),
+                bids: (490..990).rev().map(|t| tl(t, 1.0)).collect(),
+            });
+            assert_eq!(book.asks_heap.len(), 500);
+            assert_eq!(book.bids_heap.len(), 500);
+        }
 
-        book.process_tick_update(&update);
+        let global = Tracking::default();
+        let mut book = OrderBook::new_in(2u8.try_into().unwrap(), global.clone());
+        spill_burst(&mut book);
+        assert!(global.count() > 0);
 
+        // the whole burst comes out of the reserved block
+        let arena = Arena::with_capacity(1 << 20);
+        let mut book = OrderBook::new_in(2u8.try_into().unwrap(), arena.clone());
+        spill_burst(&mut book);
         println!("{book:#?}");
-        println!("{book}");
+        assert_eq!(arena.fallbacks(), 0);
+        assert!(arena.used() > 0 && arena.used() <= arena.capacity());
+
+        // a block too small for the burst only falls back for the rest
+        let small = Arena::with_capacity(1 << 12);
+        let mut book = OrderBook::new_in(2u8.try_into().unwrap(), small.clone());
+        spill_burst(&mut book);
+        assert!(small.fallbacks() > 0 && small.fallbacks() < global.count());
+        assert_eq!(book.total_levels(), 1002);
+    }
 
-        assert_eq!(book.asks_0_tick, 100);
-        assert_eq!(book.asks[1], 0.0); // tick 101 (removed)
-        assert_eq!(book.asks[2], 15.0); // tick 102 (added)
-        assert_eq!(book.asks_heap.len(), 0);
+    #[test]
+    fn heap_ratio() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.total_levels(), 0);
+        assert_eq!(book.heap_ratio(), 0.0);
 
-        assert_eq!(book.bids_0_tick, 100);
-        assert_eq!(book.bids[1], 0.0); // tick 99 (removed)
-        assert_eq!(book.bids[2], 20.0); // tick 98 (added)
-        assert_eq!(book.bids_heap.len(), 0);
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        });
+        assert_eq!(book.total_levels(), 4);
+        assert_eq!(book.heap_ratio(), 0.0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(110, 35.0), tl(111, 45.0)],
+            bids: vec![tl(90, 40.0), tl(89, 50.0)],
+        });
+        assert_eq!(book.total_levels(), 8);
+        assert_eq!(
+            book.total_levels(),
+            book.asks().count() + book.bids().count()
+        );
+        assert_eq!(book.heap_ratio(), 0.5);
     }
 
     #[test]
-    fn test_rebalance_bids_higher() {
-        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+    fn cache_utilization() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.ask_cache_utilization(), (0, 8));
+        assert_eq!(book.bid_cache_utilization(), (0, 8));
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![],
-            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(103, 15.0), tl(120, 35.0)],
+            bids: vec![tl(99, 10.0)],
         });
 
-        println!("Before rebalance:\n{book:#?}");
-        println!("{book}");
+        assert_eq!(book.ask_cache_utilization(), (2, 8));
+        assert_eq!(book.bid_cache_utilization(), (1, 8));
+        assert_eq!(book.asks_heap.len(), 1);
+    }
 
-        assert_eq!(book.bids_0_tick, 100);
-        assert_eq!(book.bids[1], 10.0); // tick 99
-        assert_eq!(book.bids[2], 20.0); // tick 98
-        assert_eq!(book.bids[3], 30.0); // tick 97
-        assert_eq!(book.bids_heap.len(), 0);
+    #[test]
+    fn one_side_updates() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
-            sequence_id: 1,
-            asks: vec![],
-            bids: vec![tl(101, 15.0)],
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
         });
+        let bids: Vec<_> = book.bid_entries().collect();
+
+        let _ = book.update_asks(1, &[tl(100, 1.0)]);
+        assert_eq!(book.best_ask().unwrap().size, 1.0);
+
+        // best removed, rescan still runs
+        let _ = book.update_asks(2, &[tl(100, 0.0), tl(101, 0.0)]);
+        assert_eq!(book.best_ask().unwrap().size, 15.0);
+
+        // window moves, rebalance still runs
+        let _ = book.update_asks(3, &[tl(90, 2.0)]);
+        assert_eq!(book.asks_0_tick, 89);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(90, 2.0), (102, 15.0)]
+        );
+
+        assert_eq!(book.sequence_id(), 3);
+        assert_eq!(book.bid_entries().collect::<Vec<_>>(), bids);
+
+        let asks: Vec<_> = book.ask_entries().collect();
+        let _ = book.update_bids(4, &[tl(99, 0.0)]);
+        let _ = book.update_bids(5, &[tl(110, 3.0)]);
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(110, 3.0), (98, 20.0)]
+        );
+        assert_eq!(book.ask_entries().collect::<Vec<_>>(), asks);
+        assert_eq!(
+            book.update_bids(6, &[tl(110, 3.0)]),
+            UpdateOutcome::Unchanged
+        );
+    }
 
-        println!("After rebalance higher:\n{book:#?}");
-        println!("{book}");
+    #[test]
+    fn in_window_fast_path_matches_general_path() {
+        let mut fast: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = fast.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: (101..=104).map(|t| tl(t, t as f64)).collect(),
+            bids: (96..=99).rev().map(|t| tl(t, t as f64)).collect(),
+        });
+        // rounding to the lot takes the general path every time
+        let mut general = fast.clone().with_size_step(0.5);
+
+        let updates = [
+            // sizes churn behind the best, all in window
+            (
+                vec![tl(101, 2.0), tl(103, 0.0), tl(105, 1.0)],
+                vec![tl(98, 0.0), tl(96, 4.0)],
+            ),
+            // best resized but kept
+            (vec![tl(101, 7.0)], vec![tl(99, 3.5)]),
+            // in window, then a level past the cache
+            (
+                vec![tl(102, 1.0), tl(120, 2.0)],
+                vec![tl(97, 1.0), tl(80, 2.0)],
+            ),
+            // best removed, falls back
+            (
+                vec![tl(101, 0.0), tl(102, 4.0)],
+                vec![tl(99, 0.0), tl(96, 0.0)],
+            ),
+            // improves the best, falls back
+            (vec![tl(100, 1.0)], vec![tl(100, 0.0), tl(98, 1.5)]),
+        ];
+
+        for (seq, (asks, bids)) in updates.into_iter().enumerate() {
+            let update = TickUpdate {
+                sequence_id: seq as u64 + 1,
+                timestamp: None,
+                asks,
+                bids,
+            };
+            let mut events = Vec::new();
+            assert_eq!(
+                fast.process_tick_update_with_events(&update, &mut events),
+                general.process_tick_update(&update)
+            );
+            assert_eq!(fast.snapshot(), general.snapshot());
+            assert_eq!(fast.best_ask_tick(), general.best_ask_tick());
+            assert_eq!(fast.best_bid_tick(), general.best_bid_tick());
+            assert_eq!(fast.validate(), Ok(()));
+            println!("{events:?}");
+        }
 
-        assert_eq!(book.bids_0_tick, 102);
-        assert_eq!(book.bids[0], 0.0); // tick 102 (empty)
-        assert_eq!(book.bids[1], 15.0); // tick 101 (new)
-        assert_eq!(book.bids[2], 0.0); // tick 100 (empty)
-        assert_eq!(book.bids[3], 10.0); // tick 99 (shifted)
-        assert_eq!(*book.bids_heap.get(&98).unwrap(), 20.0);
-        assert_eq!(*book.bids_heap.get(&97).unwrap(), 30.0);
-        assert_eq!(book.bids_heap.len(), 2);
+        println!("{fast:#?}");
+        assert_eq!(fast.ask_heap_bounds(), Some((120, 120)));
     }
 
     #[test]
-    fn test_rebalance_asks_lower() {
+    fn batch_matches_sequential() {
+        let mut sequential: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        let init = TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: (101..=106).map(|t| tl(t, t as f64)).collect(),
+            bids: (94..=99).rev().map(|t| tl(t, t as f64)).collect(),
+        };
+        let _ = sequential.process_tick_update(&init);
+        let mut batched = sequential.clone();
+
+        let updates = vec![
+            // best removed, next level deeper
+            TickUpdate {
+                sequence_id: 1,
+                timestamp: Some(10),
+                asks: vec![tl(101, 0.0), tl(102, 0.0)],
+                bids: vec![tl(99, 0.0)],
+            },
+            // beyond the window
+            TickUpdate {
+                sequence_id: 2,
+                timestamp: None,
+                asks: vec![tl(120, 1.0), tl(130, 2.0)],
+                bids: vec![tl(80, 1.0), tl(70, 2.0)],
+            },
+            // whole cache cleared, best moves into the heap
+            TickUpdate {
+                sequence_id: 3,
+                timestamp: Some(30),
+                asks: (103..=106).map(|t| tl(t, 0.0)).collect(),
+                bids: (94..=98).rev().map(|t| tl(t, 0.0)).collect(),
+            },
+            // back below the window
+            TickUpdate {
+                sequence_id: 4,
+                timestamp: None,
+                asks: vec![tl(90, 3.0), tl(120, 0.0)],
+                bids: vec![tl(88, 3.0), tl(80, 5.0)],
+            },
+        ];
+
+        for update in &updates {
+            let _ = sequential.process_tick_update(update);
+        }
+        assert_eq!(batched.apply_batch(&updates), UpdateOutcome::Changed);
+
+        println!("{batched:#?}");
+
+        assert_eq!(batched.sequence_id(), 4);
+        assert_eq!(batched.last_timestamp(), Some(30));
+        assert_eq!(
+            batched.ask_entries().collect::<Vec<_>>(),
+            sequential.ask_entries().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            batched.bid_entries().collect::<Vec<_>>(),
+            sequential.bid_entries().collect::<Vec<_>>()
+        );
+        assert_eq!(batched.best_ask(), sequential.best_ask());
+        assert_eq!(batched.best_bid(), sequential.best_bid());
+        assert_eq!(batched.state_hash(), sequential.state_hash());
+        assert_eq!(batched.validate(), Ok(()));
+
+        // resends mid batch follow the equal sequence policy
+        for policy in [
+            EqualSequencePolicy::Apply,
+            EqualSequencePolicy::Ignore,
+            EqualSequencePolicy::Replace,
+        ] {
+            let mut sequential: OrderBook<8, 2> =
+                OrderBook::new(2u8.try_into().unwrap()).with_equal_sequence_policy(policy);
+            let _ = sequential.process_tick_update(&init);
+            let mut batched = sequential.clone();
+
+            let updates = vec![
+                TickUpdate {
+                    sequence_id: 1,
+                    timestamp: None,
+                    asks: vec![tl(101, 0.0), tl(110, 4.0)],
+                    bids: vec![tl(99, 0.0)],
+                },
+                // resend of 1
+                TickUpdate {
+                    sequence_id: 1,
+                    timestamp: Some(5),
+                    asks: vec![tl(103, 0.0), tl(108, 2.0)],
+                    bids: vec![tl(97, 6.0)],
+                },
+                TickUpdate {
+                    sequence_id: 2,
+                    timestamp: None,
+                    asks: vec![tl(104, 9.0)],
+                    bids: vec![tl(96, 9.0)],
+                },
+            ];
+            for update in &updates {
+                let _ = sequential.process_tick_update(update);
+            }
+            let _ = batched.apply_batch(&updates);
+
+            assert_eq!(
+                batched.ask_entries().collect::<Vec<_>>(),
+                sequential.ask_entries().collect::<Vec<_>>(),
+                "{policy:?}"
+            );
+            assert_eq!(
+                batched.bid_entries().collect::<Vec<_>>(),
+                sequential.bid_entries().collect::<Vec<_>>(),
+                "{policy:?}"
+            );
+            assert_eq!(batched.last_timestamp(), sequential.last_timestamp());
+            assert_eq!(batched.state_hash(), sequential.state_hash());
+            assert_eq!(batched.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn cache_levels_skip_heap() {
         let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
-            bids: vec![],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
         });
 
-        println!("Before rebalance:\n{book:#?}");
-        println!("{book}");
+        let asks: Vec<_> = book.ask_cache_levels().collect();
+        let bids: Vec<_> = book.bid_cache_levels().collect();
+        assert_eq!(asks, vec![(101, 5.0), (102, 15.0)]);
+        assert_eq!(bids, vec![(99, 10.0), (98, 20.0)]);
+        assert_eq!(
+            book.ask_entries().take(asks.len()).collect::<Vec<_>>(),
+            asks
+        );
+        assert_eq!(
+            book.bid_entries().take(bids.len()).collect::<Vec<_>>(),
+            bids
+        );
+
+        let empty: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(empty.ask_cache_levels().count(), 0);
+        assert_eq!(empty.bid_cache_levels().count(), 0);
+    }
 
-        assert_eq!(book.asks_0_tick, 100);
-        assert_eq!(book.asks[1], 5.0); // tick 101
-        assert_eq!(book.asks[2], 20.0); // tick 102
-        assert_eq!(book.asks[3], 30.0); // tick 103
-        assert_eq!(book.asks_heap.len(), 0);
+    #[test]
+    fn compact() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: (101..=109).map(|t| tl(t, t as f64)).collect(),
+            bids: (91..=99).rev().map(|t| tl(t, t as f64)).collect(),
+        });
+        // best moves 2 slots in, short of the automatic recenter
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 1,
-            asks: vec![tl(99, 15.0)],
-            bids: vec![],
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, 0.0)],
+            bids: vec![tl(99, 0.0), tl(98, 0.0)],
         });
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (99, 4));
+        assert_eq!((book.bids_0_tick, book.best_bid_i), (101, 4));
+        assert_eq!(book.asks_heap.len(), 3);
+        assert_eq!(book.bids_heap.len(), 3);
 
-        println!("After rebalance lower:\n{book:#?}");
-        println!("{book}");
+        let asks: Vec<_> = book.ask_entries().collect();
+        let bids: Vec<_> = book.bid_entries().collect();
 
-        assert_eq!(book.asks_0_tick, 98);
-        assert_eq!(book.asks[0], 0.0); // tick 98 (empty)
-        assert_eq!(book.asks[1], 15.0); // tick 99 (new)
-        assert_eq!(book.asks[2], 0.0); // tick 100 (empty)
-        assert_eq!(book.asks[3], 5.0); // tick 101 (shifted)
-        assert_eq!(*book.asks_heap.get(&102).unwrap(), 20.0);
-        assert_eq!(*book.asks_heap.get(&103).unwrap(), 30.0);
-        assert_eq!(book.asks_heap.len(), 2);
+        book.compact();
+
+        println!("{book:#?}");
+
+        assert_eq!((book.asks_0_tick, book.best_ask_i), (101, 2));
+        assert_eq!((book.bids_0_tick, book.best_bid_i), (99, 2));
+        assert_eq!(
+            book.asks_heap.keys().copied().collect::<Vec<_>>(),
+            vec![109]
+        );
+        assert_eq!(book.bids_heap.keys().copied().collect::<Vec<_>>(), vec![91]);
+        assert_eq!(book.ask_entries().collect::<Vec<_>>(), asks);
+        assert_eq!(book.bid_entries().collect::<Vec<_>>(), bids);
     }
 
     #[test]
-    fn test_rebalance_bids_lower_and_update_best() {
-        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+    fn compact_towards_best_spills_by_policy() {
+        for policy in [HeapPolicy::Keep, HeapPolicy::Disabled] {
+            let mut book: OrderBook<8, 2> =
+                OrderBook::new(2u8.try_into().unwrap()).with_heap_policy(policy);
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 0,
+                timestamp: None,
+                asks: (101..=106).map(|t| tl(t, 1.0)).collect(),
+                bids: vec![],
+            });
+            // best lands on slot 0, inside the window's padding
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![tl(99, 3.0)],
+                bids: vec![],
+            });
+            assert_eq!((book.asks_0_tick, book.best_ask_i), (99, 0));
+
+            book.compact();
+            println!("{book:#?}");
+
+            assert_eq!((book.asks_0_tick, book.best_ask_i), (97, 2));
+            assert_eq!(book.best_ask_tick(), Some(99));
+            match policy {
+                HeapPolicy::Disabled => {
+                    assert_eq!(book.ask_heap_bounds(), None);
+                    assert_eq!(book.stats().dropped_spills, 2);
+                    assert_eq!(book.ask_entries().count(), 5);
+                }
+                _ => {
+                    assert_eq!(book.ask_heap_bounds(), Some((105, 106)));
+                    assert_eq!(book.stats().dropped_spills, 0);
+                    assert_eq!(book.ask_entries().count(), 7);
+                }
+            }
+        }
 
-        book.process_tick_update(&TickUpdate {
+        // nothing to spill, a window already on its best stays
+        let mut book: OrderBook<8, 2> =
+            OrderBook::new(2u8.try_into().unwrap()).with_heap_policy(HeapPolicy::Disabled);
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![],
-            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
+            timestamp: None,
+            asks: vec![tl(101, 1.0), tl(102, 2.0)],
+            bids: vec![tl(99, 1.0)],
         });
+        let before = (book.asks_0_tick, book.bids_0_tick);
+        book.compact();
+        assert_eq!((book.asks_0_tick, book.bids_0_tick), before);
+        assert_eq!(book.total_levels(), 3);
+    }
 
-        println!("Initial state:\n{book:#?}");
-        println!("{book}");
-
-        assert_eq!(book.bids_0_tick, 100);
-        assert_eq!(book.bids[1], 10.0); // tick 99
-        assert_eq!(book.bids[2], 20.0); // tick 98
-        assert_eq!(book.bids[3], 30.0); // tick 97
-        assert_eq!(book.bids_heap.len(), 0);
+    #[test]
+    fn take_crosses_into_heap() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
-            sequence_id: 1,
-            asks: vec![],
-            bids: vec![
-                tl(99, 0.0),   // Remove
-                tl(98, 0.0),   // Remove
-                tl(97, 35.0),  // Update size
-                tl(95, 50.0),  // Add new
-                tl(86, 100.0), // Add new much lower
-            ],
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0), tl(111, 45.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0), tl(89, 50.0)],
         });
 
-        println!("After rebalance lower:\n{book:#?}");
-        println!("{book}");
-        assert_eq!(book.bids_0_tick, 98);
-        assert_eq!(book.best_bid_i, 1);
-        assert_eq!(book.bids[0], 0.0);
-        assert_eq!(book.bids[1], 35.0); // tick 97
-        assert_eq!(book.bids[2], 0.0);
-        assert_eq!(book.bids[3], 50.0); // tick 95
-        assert_eq!(*book.bids_heap.get(&86).unwrap(), 100.0);
-        assert_eq!(book.bids_heap.len(), 1);
+        assert_eq!(
+            book.ask_entries().take(3).collect::<Vec<_>>(),
+            vec![(101, 5.0), (102, 15.0), (110, 35.0)]
+        );
+        assert_eq!(
+            book.bid_entries().take(3).collect::<Vec<_>>(),
+            vec![(99, 10.0), (98, 20.0), (90, 40.0)]
+        );
+        // reversed the heap comes first
+        assert_eq!(
+            book.ask_entries().rev().take(1).collect::<Vec<_>>(),
+            vec![(111, 45.0)]
+        );
     }
 
     #[test]
-    fn test_rebalance_asks_higher_and_update_best() {
-        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+    fn merge_overlapping_snapshots() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.merge_snapshot(&TickUpdate {
             sequence_id: 0,
-            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
-            bids: vec![],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(103, 25.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
         });
 
-        println!("Initial state:\n{book:#?}");
-        println!("{book}");
-
-        assert_eq!(book.asks_0_tick, 100);
-        assert_eq!(book.asks[1], 5.0); // tick 101
-        assert_eq!(book.asks[2], 20.0); // tick 102
-        assert_eq!(book.asks[3], 30.0); // tick 103
-        assert_eq!(book.asks_heap.len(), 0);
-
-        book.process_tick_update(&TickUpdate {
+        // overlaps 103/97, reaches further out, carries a zero
+        let _ = book.merge_snapshot(&TickUpdate {
             sequence_id: 1,
-            asks: vec![
-                tl(101, 0.0),   // Remove
-                tl(102, 0.0),   // Remove
-                tl(103, 35.0),  // Update size
-                tl(105, 50.0),  // Add new
-                tl(114, 100.0), // Add new much higher
-            ],
-            bids: vec![],
+            timestamp: None,
+            asks: vec![tl(102, 0.0), tl(103, 26.0), tl(104, 36.0), tl(110, 46.0)],
+            bids: vec![tl(98, 0.0), tl(97, 31.0), tl(96, 41.0), tl(90, 51.0)],
         });
 
-        println!("After rebalance higher:\n{book:#?}");
         println!("{book}");
 
-        assert_eq!(book.asks_0_tick, 102);
-        assert_eq!(book.best_ask_i, 1);
-
-        assert_eq!(book.asks[0], 0.0);
-        assert_eq!(book.asks[1], 35.0); // tick 103
-        assert_eq!(book.asks[2], 0.0);
-        assert_eq!(book.asks[3], 50.0); // tick 105
-        assert_eq!(*book.asks_heap.get(&114).unwrap(), 100.0);
-        assert_eq!(book.asks_heap.len(), 1);
+        assert_eq!(book.sequence_id(), 1);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![
+                (101, 5.0),
+                (102, 15.0),
+                (103, 26.0),
+                (104, 36.0),
+                (110, 46.0)
+            ]
+        );
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(99, 10.0), (98, 20.0), (97, 31.0), (96, 41.0), (90, 51.0)]
+        );
     }
 
     #[test]
-    fn test_new_best_ask_i_lower_without_rebalance() {
+    fn state_hash_ignores_layout() {
         let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
-            bids: vec![],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
+        });
+        // rebalances both windows
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, 0.0), tl(108, 1.0)],
+            bids: vec![tl(99, 0.0), tl(98, 0.0), tl(93, 2.0)],
         });
 
-        println!("Initial state:\n{book:#?}");
-        println!("{book}");
+        // wider cache, every level cached
+        let fresh: OrderBook<16, 2> = OrderBook::from_levels(
+            2u8.try_into().unwrap(),
+            1,
+            &[tl(108, 1.0), tl(110, 35.0)],
+            &[tl(93, 2.0), tl(90, 40.0)],
+        );
 
-        assert_eq!(book.asks_0_tick, 100);
-        assert_eq!(book.best_ask_i, 1);
-        assert_eq!(book.asks[1], 5.0); // tick 101
-        assert_eq!(book.asks[2], 20.0); // tick 102
-        assert_eq!(book.asks[3], 30.0); // tick 103
-        assert_eq!(book.asks_heap.len(), 0);
+        assert_eq!(book.bids_heap.len(), 1);
+        assert!(fresh.bids_heap.is_empty());
+        assert_eq!(book.state_hash(), fresh.state_hash());
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 1,
-            asks: vec![tl(100, 1.0)],
+            timestamp: None,
+            asks: vec![tl(110, 36.0)],
             bids: vec![],
         });
-
-        println!("After rebalance higher:\n{book:#?}");
-        println!("{book}");
-
-        assert_eq!(book.best_ask_i, 0);
-        assert_eq!(book.asks[0], 1.0); // tick 100
+        assert_ne!(book.state_hash(), fresh.state_hash());
     }
 
     #[test]
-    fn test_new_best_ask_i_higher_without_rebalance() {
+    fn events() {
         let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
-            bids: vec![],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
         });
 
-        println!("Initial state:\n{book:#?}");
-        println!("{book}");
+        // removes the best, adds a deeper level, resizes one
+        let mut events = Vec::new();
+        let _ = book.process_tick_update_with_events(
+            &TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![tl(101, 0.0), tl(105, 7.0)],
+                bids: vec![tl(98, 21.0)],
+            },
+            &mut events,
+        );
+
+        println!("{events:#?}");
+
+        assert_eq!(
+            events,
+            vec![
+                BookEvent::LevelRemoved {
+                    side: Side::Ask,
+                    tick: 101,
+                    size: 5.0
+                },
+                BookEvent::LevelAdded {
+                    side: Side::Ask,
+                    tick: 105,
+                    size: 7.0
+                },
+                BookEvent::BestChanged {
+                    side: Side::Ask,
+                    tick: Some(102)
+                },
+                BookEvent::LevelResized {
+                    side: Side::Bid,
+                    tick: 98,
+                    old_size: 20.0,
+                    size: 21.0
+                },
+            ]
+        );
+
+        // window moves
+        events.clear();
+        let _ = book.process_tick_update_with_events(
+            &TickUpdate {
+                sequence_id: 2,
+                timestamp: None,
+                asks: vec![tl(90, 1.0)],
+                bids: vec![],
+            },
+            &mut events,
+        );
+        assert_eq!(
+            events,
+            vec![
+                BookEvent::LevelAdded {
+                    side: Side::Ask,
+                    tick: 90,
+                    size: 1.0
+                },
+                BookEvent::Rebalanced {
+                    side: Side::Ask,
+                    from_0_tick: 100,
+                    to_0_tick: 89
+                },
+                BookEvent::BestChanged {
+                    side: Side::Ask,
+                    tick: Some(90)
+                },
+            ]
+        );
+    }
 
-        assert_eq!(book.asks_0_tick, 100);
-        assert_eq!(book.best_ask_i, 1);
-        assert_eq!(book.asks[1], 5.0); // tick 101
-        assert_eq!(book.asks[2], 20.0); // tick 102
-        assert_eq!(book.asks[3], 30.0); // tick 103
-        assert_eq!(book.asks_heap.len(), 0);
+    #[test]
+    fn apply_capturing_prev() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let (bid, ask) = book.apply_capturing_prev(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0)],
+        });
+        assert_eq!((bid, ask), (None, None));
 
-        book.process_tick_update(&TickUpdate {
+        let before = (book.best_bid(), book.best_ask());
+        let (bid, ask) = book.apply_capturing_prev(&TickUpdate {
             sequence_id: 1,
+            timestamp: None,
             asks: vec![tl(101, 0.0)],
-            bids: vec![],
+            bids: vec![tl(100, 2.0)],
         });
+        println!("{book:#?}");
 
-        println!("After rebalance higher:\n{book:#?}");
-        println!("{book}");
-
-        assert_eq!(book.asks_0_tick, 100);
-        assert_eq!(book.best_ask_i, 2);
-        assert_eq!(book.asks[1], 0.0); // tick 101
-        assert_eq!(book.asks[2], 20.0); // tick 102
+        assert_eq!((bid, ask), before);
+        assert_eq!(ask.unwrap().price, 1.01);
+        assert_eq!(book.best_ask().unwrap().price, 1.02);
+        assert_eq!(bid.unwrap().price, 0.99);
+        assert_eq!(book.best_bid().unwrap().price, 1.0);
     }
 
     #[test]
-    fn test_new_best_bid_i_higher_without_rebalance() {
+    fn spread_after_update() {
         let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
 
-        book.process_tick_update(&TickUpdate {
+        let (_, spread) = book.process_tick_update_with_spread(&TickUpdate {
             sequence_id: 0,
-            asks: vec![],
-            bids: vec![tl(99, 5.0), tl(98, 20.0), tl(97, 30.0)],
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(103, 15.0)],
+            bids: vec![],
         });
+        assert_eq!(spread, None);
 
-        println!("Initial state:\n{book:#?}");
-        println!("{book}");
-
-        assert_eq!(book.bids_0_tick, 100);
-        assert_eq!(book.best_bid_i, 1);
-        assert_eq!(book.bids[1], 5.0); // tick 99
-        assert_eq!(book.bids[2], 20.0); // tick 98
-        assert_eq!(book.bids[3], 30.0); // tick 97
-        assert_eq!(book.bids_heap.len(), 0);
-
-        book.process_tick_update(&TickUpdate {
+        let (_, spread) = book.process_tick_update_with_spread(&TickUpdate {
             sequence_id: 1,
+            timestamp: None,
             asks: vec![],
-            bids: vec![tl(100, 1.0)],
+            bids: vec![tl(99, 10.0), tl(97, 20.0)],
         });
+        assert_eq!(spread, Some(2));
 
-        println!("After update:\n{book:#?}");
-        println!("{book}");
-
-        assert_eq!(book.best_bid_i, 0);
-        assert_eq!(book.bids[0], 1.0); // tick 100
+        // both bests pulled, spread widens
+        let (outcome, spread) = book.process_tick_update_with_spread(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(101, 0.0)],
+            bids: vec![tl(99, 0.0)],
+        });
+        assert_eq!(outcome, UpdateOutcome::Changed);
+        assert_eq!(spread, Some(6));
+        assert_eq!(book.spread_ticks(), spread);
+
+        // crossed
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 3,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(104, 1.0)],
+        });
+        assert_eq!(book.spread_ticks(), None);
     }
 
     #[test]
-    fn test_new_best_bid_i_lower_without_rebalance() {
-        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+    fn default_book() {
+        let mut book: OrderBook<4, 1> = OrderBook::default();
 
-        book.process_tick_update(&TickUpdate {
+        assert!(book.is_empty());
+        assert_eq!(book.tick_decimals, Decimals::new(2u8).unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
             sequence_id: 0,
-            asks: vec![],
-            bids: vec![tl(99, 5.0), tl(98, 20.0), tl(97, 30.0)],
+            timestamp: None,
+            asks: vec![tl(101, 5.0)],
+            bids: vec![tl(99, 10.0)],
         });
+        assert_eq!(book.best_ask().unwrap().price, 1.01);
+        assert_eq!(book.best_bid().unwrap().price, 0.99);
+    }
 
-        println!("Initial state:\n{book:#?}");
-        println!("{book}");
+    #[test]
+    fn tracked_totals_match_resum() {
+        // xorshift, deterministic without a rand dependency
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move |n: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % n
+        };
 
-        assert_eq!(book.bids_0_tick, 100);
-        assert_eq!(book.best_bid_i, 1);
-        assert_eq!(book.bids[1], 5.0); // tick 99
-        assert_eq!(book.bids[2], 20.0); // tick 98
-        assert_eq!(book.bids[3], 30.0); // tick 97
-        assert_eq!(book.bids_heap.len(), 0);
+        for policy in [HeapPolicy::Keep, HeapPolicy::DropBeyond(20)] {
+            let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap())
+                .with_heap_policy(policy)
+                .with_total_tracking();
+
+            let mut mid = 10_000;
+            for seq in 0..10_000 {
+                // drifting mid so levels keep crossing the cache window edge
+                mid = (mid + next(7) as u32).saturating_sub(3);
+                let mut levels = |above: bool| {
+                    let mut levels: Vec<_> = (0..next(6))
+                        .map(|_| {
+                            let offset = 1 + next(40) as u32;
+                            let tick = if above { mid + offset } else { mid - offset };
+                            // a third of the levels are removals, some sent as
+                            // negative or dust sizes
+                            let size = match next(9) {
+                                0 => 0.0,
+                                1 => -(next(10) as f64) - 0.5,
+                                2 => EPSILON / 2.0,
+                                n => 0.25 * n as f64 * next(10) as f64,
+                            };
+                            tl(tick, size)
+                        })
+                        .collect();
+                    levels.sort_by_key(|l| if above { l.tick } else { u32::MAX - l.tick });
+                    levels.dedup_by_key(|l| l.tick);
+                    levels
+                };
+                let update = TickUpdate {
+                    sequence_id: seq,
+                    timestamp: None,
+                    asks: levels(true),
+                    bids: levels(false),
+                };
+                let _ = book.process_tick_update(&update);
+
+                if seq % 1000 == 999 {
+                    book.truncate_depth(30);
+                }
 
-        book.process_tick_update(&TickUpdate {
-            sequence_id: 1,
-            asks: vec![],
-            bids: vec![tl(99, 0.0)],
+                let asks: f64 = book.ask_entries().map(|(_, size)| size).sum();
+                let bids: f64 = book.bid_entries().map(|(_, size)| size).sum();
+                assert!(
+                    (book.total_ask_size() - asks).abs() < 1e-9,
+                    "seq {seq}: tracked {} resummed {asks}",
+                    book.total_ask_size()
+                );
+                assert!((book.total_bid_size() - bids).abs() < 1e-9);
+            }
+            println!("{book:#?}");
+            assert!(book.total_levels() > 0);
+        }
+
+        // tracking picks up levels already there
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(120, 15.0)],
+            bids: vec![tl(99, 10.0)],
         });
+        let mut book = book.with_total_tracking();
+        assert_eq!(book.total_ask_size(), 20.0);
+        assert_eq!(book.total_bid_size(), 10.0);
 
-        println!("After update:\n{book:#?}");
-        println!("{book}");
+        // a negative size removes the level, it doesn't subtract
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, -3.0), tl(120, EPSILON / 2.0)],
+            bids: vec![tl(99, -3.0)],
+        });
+        assert_eq!(book.total_ask_size(), 0.0);
+        assert_eq!(book.total_bid_size(), 0.0);
+        assert!(book.is_empty());
+    }
 
-        assert_eq!(book.bids_0_tick, 100);
-        assert_eq!(book.best_bid_i, 2);
-        assert_eq!(book.bids[1], 0.0); // tick 99
-        assert_eq!(book.bids[2], 20.0); // tick 98
+    #[test]
+    fn send_and_sync() {
+        // features are additive, none of them may cost the book Sync
+        fn assert_send_sync<B: Send + Sync>() {}
+        assert_send_sync::<OrderBook<4, 1>>();
+        assert_send_sync::<OrderBook<4, 1, u64>>();
+        #[cfg(feature = "allocator_api")]
+        assert_send_sync::<OrderBook<4, 1, u32, crate::heap_alloc::Arena>>();
     }
 }
@@ -0,0 +1,525 @@
+use crate::{OrderBook, Side, cache_storage::CacheStorage, heap_alloc::HeapAlloc, tick::Tick};
+
+/// where a histogram puts levels beyond its last bin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HistogramOverflow {
+    /// leave them out
+    #[default]
+    Drop,
+    /// add them to the last bin
+    LastBin,
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    /// raw `(tick, size)` levels of one side, best first
+    pub(crate) fn side_entries(&self, side: Side) -> impl Iterator<Item = (T, f64)> {
+        let (asks, bids) = match side {
+            Side::Ask => (Some(self.ask_entries()), None),
+            Side::Bid => (None, Some(self.bid_entries())),
+        };
+
+        asks.into_iter().flatten().chain(bids.into_iter().flatten())
+    }
+
+    /// best ask price minus best bid price, negative when crossed. `None`
+    /// when a side is empty
+    ///
+    /// NOTE: like every analytic here `None` while suppressed as stale, see
+    /// `with_stale_suppression`
+    pub fn spread(&self) -> Option<f64> {
+        if self.suppressed() {
+            return None;
+        }
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+
+    /// halfway between best bid and best ask, `None` when a side is empty
+    pub fn mid_price(&self) -> Option<f64> {
+        if self.suppressed() {
+            return None;
+        }
+        Some((self.best_ask()?.price + self.best_bid()?.price) / 2.0)
+    }
+
+    /// spread over mid in basis points. `None` when a side is empty, the book
+    /// is crossed or the mid is zero
+    pub fn spread_bps(&self) -> Option<f64> {
+        let spread = self.spread()?;
+        let mid = self.mid_price()?;
+        if spread < 0.0 || mid <= 0.0 {
+            return None;
+        }
+
+        Some(spread / mid * 10_000.0)
+    }
+
+    /// `(tick, cumulative size)` of one side, best first
+    pub fn cumulative_depth(&self, side: Side) -> impl Iterator<Item = (T, f64)> {
+        self.side_entries(side).scan(0.0, |total, (tick, size)| {
+            *total += size;
+            Some((tick, *total))
+        })
+    }
+
+    /// price at which `fraction` of the top `n_levels` cumulative size of one
+    /// side sits, interpolated linearly between the two levels straddling it.
+    /// `fraction` is clamped to [0, 1], 0 gives the best price.
+    ///
+    /// `None` when the side is empty or `n_levels` is 0
+    pub fn price_at_depth_fraction(
+        &self,
+        side: Side,
+        fraction: f64,
+        n_levels: usize,
+    ) -> Option<f64> {
+        if self.suppressed() {
+            return None;
+        }
+
+        let total: f64 = self
+            .side_entries(side)
+            .take(n_levels)
+            .map(|(_, sz)| sz)
+            .sum();
+        let target = fraction.clamp(0.0, 1.0) * total;
+
+        let mut prev: Option<(f64, f64)> = None;
+        for (tick, cumulative) in self.cumulative_depth(side).take(n_levels) {
+            let price = self.tick_decimals.tick_to_f64(tick);
+            if cumulative >= target {
+                let Some((prev_price, prev_cumulative)) = prev else {
+                    return Some(price);
+                };
+                let weight = (target - prev_cumulative) / (cumulative - prev_cumulative);
+                return Some(prev_price + weight * (price - prev_price));
+            }
+            prev = Some((price, cumulative));
+        }
+
+        // empty side, no levels taken or a NaN fraction
+        None
+    }
+
+    /// ask size summed into `bins` bins of `bin_ticks` ticks each, bin `k`
+    /// covering ticks `[best + k * bin_ticks, best + (k + 1) * bin_ticks)`.
+    /// all zeros when there are no asks
+    pub fn ask_histogram(
+        &self,
+        bin_ticks: u32,
+        bins: usize,
+        overflow: HistogramOverflow,
+    ) -> Vec<f64> {
+        self.histogram(Side::Ask, bin_ticks, bins, overflow)
+    }
+
+    /// bid size binned by distance below the best bid, see `ask_histogram`
+    pub fn bid_histogram(
+        &self,
+        bin_ticks: u32,
+        bins: usize,
+        overflow: HistogramOverflow,
+    ) -> Vec<f64> {
+        self.histogram(Side::Bid, bin_ticks, bins, overflow)
+    }
+
+    fn histogram(
+        &self,
+        side: Side,
+        bin_ticks: u32,
+        bins: usize,
+        overflow: HistogramOverflow,
+    ) -> Vec<f64> {
+        assert!(bin_ticks > 0, "histogram bins must span at least one tick");
+
+        let mut histogram = vec![0.0; bins];
+        let Some(last) = bins.checked_sub(1) else {
+            return histogram;
+        };
+
+        let mut best_tick = None;
+        for (tick, size) in self.side_entries(side) {
+            let best_tick = *best_tick.get_or_insert(tick);
+            let bin = best_tick.abs_diff(tick).to_index() / bin_ticks as usize;
+            if bin <= last {
+                histogram[bin] += size;
+            } else if overflow == HistogramOverflow::LastBin {
+                histogram[last] += size;
+            } else {
+                // levels come best first, the rest are further out still
+                break;
+            }
+        }
+
+        histogram
+    }
+
+    /// least squares slope of cumulative size against tick distance over the
+    /// top `levels` levels of one side, in size per tick
+    ///
+    /// distance is measured from the side's best, which gives the same slope
+    /// as measuring from the mid. `None` with fewer than 2 populated levels
+    pub fn liquidity_slope(&self, side: Side, levels: usize) -> Option<f64> {
+        if self.suppressed() {
+            return None;
+        }
+
+        let mut best_tick = None;
+        let points = self
+            .cumulative_depth(side)
+            .take(levels)
+            .map(|(tick, total)| {
+                let best_tick = *best_tick.get_or_insert(tick);
+                (best_tick.abs_diff(tick).to_f64(), total)
+            });
+
+        let (mut n, mut sum_x, mut sum_y, mut sum_xx, mut sum_xy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (x, y) in points {
+            n += 1.0;
+            sum_x += x;
+            sum_y += y;
+            sum_xx += x * x;
+            sum_xy += x * y;
+        }
+
+        if n < 2.0 {
+            return None;
+        }
+
+        Some((n * sum_xy - sum_x * sum_y) / (n * sum_xx - sum_x * sum_x))
+    }
+
+    /// top `n` levels of one side as `(ticks from the mid tick, size)`, best
+    /// first. bids are negative and asks positive. empty when a side is empty
+    /// or while suppressed as stale
+    ///
+    /// the mid tick rounds down on an odd spread, so a one tick spread puts
+    /// the best bid at 0
+    pub fn normalized_levels(&self, side: Side, n: usize) -> Vec<(i64, f64)> {
+        let Some(mid) = self.mid_tick() else {
+            return Vec::new();
+        };
+
+        self.side_entries(side)
+            .take(n)
+            .map(|(tick, size)| ((tick.to_u64() as i128 - mid) as i64, size))
+            .collect()
+    }
+
+    /// every level of both sides as `(ticks from the mid tick, size)`,
+    /// nearest the mid first, bids negative and asks positive. on equal
+    /// distance the ask comes first. empty when a side is empty or while
+    /// suppressed as stale, the mid tick is as in `normalized_levels`
+    pub fn levels_by_distance(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        let mid = self.mid_tick();
+        let signed = move |(tick, size): (T, f64)| {
+            // no mid means nothing is yielded, the 0 is never seen
+            let distance = tick.to_u64() as i128 - mid.unwrap_or(0);
+            (distance as i64, size)
+        };
+        let mut asks = self.side_entries(Side::Ask).map(signed).peekable();
+        let mut bids = self.side_entries(Side::Bid).map(signed).peekable();
+
+        std::iter::from_fn(move || {
+            mid?;
+            match (asks.peek(), bids.peek()) {
+                (Some(ask), Some(bid)) if ask.0 <= -bid.0 => asks.next(),
+                (_, Some(_)) => bids.next(),
+                (Some(_), None) => asks.next(),
+                (None, None) => None,
+            }
+        })
+    }
+
+    /// best ask and best bid tick averaged, rounded down. `None` when a side
+    /// is empty or while suppressed as stale
+    fn mid_tick(&self) -> Option<i128> {
+        if self.suppressed() {
+            return None;
+        }
+        let (ask, bid) = (self.best_ask_tick()?, self.best_bid_tick()?);
+        Some((ask.to_u64() as i128 + bid.to_u64() as i128).div_euclid(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HistogramOverflow, OrderBook, Side, TickLevel, TickUpdate};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn cumulative_depth() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 25.0)],
+            bids: vec![tl(99, 10.0), tl(90, 20.0)],
+        });
+
+        assert_eq!(
+            book.cumulative_depth(Side::Ask).collect::<Vec<_>>(),
+            vec![(101, 5.0), (102, 20.0), (110, 45.0)]
+        );
+        assert_eq!(
+            book.cumulative_depth(Side::Bid).collect::<Vec<_>>(),
+            vec![(99, 10.0), (90, 30.0)]
+        );
+    }
+
+    #[test]
+    fn liquidity_slope() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        // 2.0 per tick on asks, 3.0 every other tick on bids, spilling into the heap
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: (101..111).map(|tick| tl(tick, 2.0)).collect(),
+            bids: (0..10).map(|i| tl(99 - i * 2, 3.0)).collect(),
+        });
+
+        let ask_slope = book.liquidity_slope(Side::Ask, 10).unwrap();
+        let bid_slope = book.liquidity_slope(Side::Bid, 10).unwrap();
+
+        assert!((ask_slope - 2.0).abs() < 1e-9);
+        assert!((bid_slope - 1.5).abs() < 1e-9);
+        assert!((book.liquidity_slope(Side::Ask, 3).unwrap() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn liquidity_slope_too_few_levels() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0)],
+        });
+
+        assert_eq!(book.liquidity_slope(Side::Bid, 10), None);
+        assert_eq!(book.liquidity_slope(Side::Ask, 1), None);
+        assert!(book.liquidity_slope(Side::Ask, 2).is_some());
+    }
+
+    #[test]
+    fn price_at_depth_fraction() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.price_at_depth_fraction(Side::Ask, 0.5, 10), None);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 10.0), tl(102, 10.0), tl(103, 20.0)],
+            bids: vec![tl(99, 30.0), tl(98, 10.0)],
+        });
+
+        let price = |side, fraction, n_levels| {
+            book.price_at_depth_fraction(side, fraction, n_levels)
+                .unwrap()
+        };
+
+        // cumulative 10, 20, 40: half of 40 sits exactly at 1.02
+        assert!((price(Side::Ask, 0.5, 10) - 1.02).abs() < 1e-9);
+        assert!((price(Side::Ask, 0.75, 10) - 1.025).abs() < 1e-9);
+        assert!((price(Side::Ask, 0.5, 2) - 1.01).abs() < 1e-9);
+        assert!((price(Side::Bid, 0.5, 10) - 0.99).abs() < 1e-9);
+
+        // clamped
+        assert!((price(Side::Ask, -1.0, 10) - 1.01).abs() < 1e-9);
+        assert!((price(Side::Ask, 2.0, 10) - 1.03).abs() < 1e-9);
+        assert_eq!(book.price_at_depth_fraction(Side::Ask, 0.5, 0), None);
+    }
+
+    #[test]
+    fn histograms() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(
+            book.ask_histogram(2, 3, HistogramOverflow::Drop),
+            vec![0.0; 3]
+        );
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![
+                tl(101, 1.0),
+                tl(102, 2.0),
+                tl(103, 4.0),
+                tl(106, 8.0),
+                tl(120, 16.0),
+            ],
+            bids: vec![tl(99, 1.0), tl(96, 2.0), tl(95, 4.0)],
+        });
+
+        // ask bins [101, 103), [103, 105), [105, 107)
+        assert_eq!(
+            book.ask_histogram(2, 3, HistogramOverflow::Drop),
+            vec![3.0, 4.0, 8.0]
+        );
+        assert_eq!(
+            book.ask_histogram(2, 3, HistogramOverflow::LastBin),
+            vec![3.0, 4.0, 24.0]
+        );
+        // bid bins (97, 99], (95, 97]
+        assert_eq!(
+            book.bid_histogram(2, 2, HistogramOverflow::Drop),
+            vec![1.0, 2.0]
+        );
+        assert_eq!(
+            book.bid_histogram(2, 2, HistogramOverflow::LastBin),
+            vec![1.0, 6.0]
+        );
+        assert!(
+            book.bid_histogram(2, 0, HistogramOverflow::LastBin)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn spread_bps() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.spread_bps(), None);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(10010, 5.0)],
+            bids: vec![tl(9990, 10.0)],
+        });
+
+        // 100.10 - 99.90 = 0.20 over a 100.00 mid = 20bps
+        assert!((book.spread().unwrap() - 0.2).abs() < 1e-9);
+        assert!((book.mid_price().unwrap() - 100.0).abs() < 1e-9);
+        assert!((book.spread_bps().unwrap() - 20.0).abs() < 1e-9);
+
+        // crossed
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(10020, 1.0)],
+        });
+        assert!(book.spread().unwrap() < 0.0);
+        assert_eq!(book.spread_bps(), None);
+    }
+
+    #[test]
+    fn stale_suppresses_analytics() {
+        let update = TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        };
+
+        let mut book: OrderBook<4, 1> =
+            OrderBook::new(2u8.try_into().unwrap()).with_stale_suppression(true);
+        let _ = book.process_tick_update(&update);
+        assert!(!book.is_stale());
+        assert!((book.mid_price().unwrap() - 1.0).abs() < 1e-9);
+
+        book.set_stale(true);
+        assert!(book.is_stale());
+        assert_eq!(book.mid_price(), None);
+        assert_eq!(book.spread_bps(), None);
+        assert_eq!(book.liquidity_slope(Side::Ask, 2), None);
+        // levels stay queryable
+        assert_eq!(book.asks().count(), 2);
+        assert_eq!(book.best_bid().unwrap().size, 10.0);
+
+        book.set_stale(false);
+        assert!(book.mid_price().is_some());
+
+        // without suppression stale is only reported
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&update);
+        book.set_stale(true);
+        assert!(book.mid_price().is_some());
+    }
+
+    #[test]
+    fn spread_bps_zero_mid() {
+        let book: OrderBook<4, 1> =
+            OrderBook::from_levels(2u8.try_into().unwrap(), 0, &[tl(0, 1.0)], &[tl(0, 1.0)]);
+
+        assert_eq!(book.mid_price(), Some(0.0));
+        assert_eq!(book.spread_bps(), None);
+    }
+
+    #[test]
+    fn normalized_levels() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert!(book.normalized_levels(Side::Ask, 3).is_empty());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 25.0)],
+            bids: vec![tl(99, 5.0), tl(98, 15.0), tl(90, 25.0)],
+        });
+        println!("{book:#?}");
+
+        let asks = book.normalized_levels(Side::Ask, 3);
+        let bids = book.normalized_levels(Side::Bid, 3);
+        assert_eq!(asks, vec![(1, 5.0), (2, 15.0), (10, 25.0)]);
+        assert_eq!(bids, vec![(-1, 5.0), (-2, 15.0), (-10, 25.0)]);
+        assert_eq!(book.normalized_levels(Side::Bid, 1), vec![(-1, 5.0)]);
+
+        // one side gone, no mid
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, 0.0), tl(110, 0.0)],
+            bids: vec![],
+        });
+        assert!(book.normalized_levels(Side::Bid, 3).is_empty());
+    }
+
+    #[test]
+    fn levels_by_distance() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.levels_by_distance().count(), 0);
+
+        // symmetric around 100, one side deeper
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 1.0), tl(103, 3.0), tl(104, 4.0), tl(120, 20.0)],
+            bids: vec![tl(99, 1.0), tl(97, 3.0), tl(96, 4.0)],
+        });
+        println!("{book:#?}");
+
+        assert_eq!(
+            book.levels_by_distance().collect::<Vec<_>>(),
+            vec![
+                (1, 1.0),
+                (-1, 1.0),
+                (3, 3.0),
+                (-3, 3.0),
+                (4, 4.0),
+                (-4, 4.0),
+                (20, 20.0),
+            ]
+        );
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(99, 0.0), tl(97, 0.0), tl(96, 0.0)],
+        });
+        assert_eq!(book.levels_by_distance().count(), 0);
+    }
+}
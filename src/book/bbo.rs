@@ -0,0 +1,184 @@
+use crate::{
+    FloatLevel, TickLevel, TickUpdate, UpdateOutcome, populated,
+    tick::{Decimals, Tick},
+};
+
+/// book tracking only the best level of each side, for when BBO is all that's
+/// needed across many symbols.
+///
+/// NOTE: with no depth it can't recover the next best on its own. when the
+/// best is removed the new best is the best live level in that same update,
+/// so the feed must carry enough depth. if it doesn't the side goes empty, or
+/// a worse level than the true best is picked up, until a better one arrives
+#[derive(Debug, Clone)]
+pub struct BboBook<T: Tick = u32> {
+    sequence_id: u64,
+    tick_decimals: Decimals,
+    best_ask: Option<TickLevel<T>>,
+    best_bid: Option<TickLevel<T>>,
+}
+
+impl<T: Tick> BboBook<T> {
+    pub fn new(tick_decimals: Decimals) -> Self {
+        Self {
+            sequence_id: 0,
+            tick_decimals,
+            best_ask: None,
+            best_bid: None,
+        }
+    }
+
+    /// NOTE: update ordering not handled by book. this always updates book
+    ///
+    /// `Changed` when the best level of either side changed
+    pub fn process_tick_update(&mut self, update: &TickUpdate<T>) -> UpdateOutcome {
+        self.sequence_id = update.sequence_id;
+
+        let best_ask = top(self.best_ask, update.asks(), |a, b| a < b);
+        let best_bid = top(self.best_bid, update.bids(), |a, b| a > b);
+
+        let changed = best_ask != self.best_ask || best_bid != self.best_bid;
+        self.best_ask = best_ask;
+        self.best_bid = best_bid;
+
+        if changed {
+            UpdateOutcome::Changed
+        } else {
+            UpdateOutcome::Unchanged
+        }
+    }
+
+    pub fn sequence_id(&self) -> u64 {
+        self.sequence_id
+    }
+
+    /// `None` when there are no asks
+    pub fn best_ask(&self) -> Option<FloatLevel> {
+        self.best_ask.map(|l| self.to_float(l))
+    }
+
+    /// `None` when there are no bids
+    pub fn best_bid(&self) -> Option<FloatLevel> {
+        self.best_bid.map(|l| self.to_float(l))
+    }
+
+    pub fn best_ask_tick(&self) -> Option<TickLevel<T>> {
+        self.best_ask
+    }
+
+    pub fn best_bid_tick(&self) -> Option<TickLevel<T>> {
+        self.best_bid
+    }
+
+    fn to_float(&self, level: TickLevel<T>) -> FloatLevel {
+        FloatLevel {
+            price: self.tick_decimals.tick_to_f64(level.tick),
+            size: level.size,
+        }
+    }
+}
+
+/// new best of one side given its current best and the side's update levels
+/// sorted best first
+fn top<T: Tick>(
+    best: Option<TickLevel<T>>,
+    levels: impl Iterator<Item = TickLevel<T>>,
+    better: impl Fn(T, T) -> bool,
+) -> Option<TickLevel<T>> {
+    let mut best = best;
+    let mut first_live = None;
+
+    for level in levels {
+        if best.is_some_and(|b| b.tick == level.tick) {
+            best = populated(level.size).then_some(level);
+        }
+        if first_live.is_none() && populated(level.size) {
+            first_live = Some(level);
+        }
+    }
+
+    match (best, first_live) {
+        (Some(b), Some(l)) if better(l.tick, b.tick) => Some(l),
+        (best, first_live) => best.or(first_live),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BboBook;
+    use crate::{TickLevel, TickUpdate, UpdateOutcome};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn tracks_top_of_book() {
+        let mut book: BboBook = BboBook::new(2u8.try_into().unwrap());
+        assert!(book.best_ask().is_none());
+        assert!(book.best_bid().is_none());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        });
+
+        println!("{book:#?}");
+
+        assert_eq!(book.best_ask_tick(), Some(tl(101, 5.0)));
+        assert_eq!(book.best_bid_tick(), Some(tl(99, 10.0)));
+        assert_eq!(book.best_ask().unwrap().price, 1.01);
+
+        // worse levels don't move the top, resizing the best does
+        let outcome = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(103, 1.0)],
+            bids: vec![tl(97, 1.0)],
+        });
+        assert_eq!(outcome, UpdateOutcome::Unchanged);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(101, 6.0)],
+            bids: vec![tl(100, 2.0)],
+        });
+        assert_eq!(book.best_ask_tick(), Some(tl(101, 6.0)));
+        assert_eq!(book.best_bid_tick(), Some(tl(100, 2.0)));
+    }
+
+    #[test]
+    fn best_removed() {
+        let mut book: BboBook = BboBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        });
+
+        // update carries the next level
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, 15.0)],
+            bids: vec![tl(99, 0.0), tl(98, 20.0)],
+        });
+        assert_eq!(book.best_ask_tick(), Some(tl(102, 15.0)));
+        assert_eq!(book.best_bid_tick(), Some(tl(98, 20.0)));
+
+        // it doesn't, the side can't be recovered
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(102, 0.0)],
+            bids: vec![tl(98, 0.0)],
+        });
+        assert!(book.best_ask().is_none());
+        assert!(book.best_bid().is_none());
+    }
+}
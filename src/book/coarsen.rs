@@ -0,0 +1,149 @@
+use crate::{
+    OrderBook, Side, TickLevel, cache_storage::CacheStorage, heap_alloc::HeapAlloc, tick::Tick,
+};
+
+/// how `coarsen` puts fine ticks onto coarse ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoarseRounding {
+    /// asks down and bids up, toward the mid. the tightest picture, but a
+    /// book tighter than a coarse tick can come out locked or crossed
+    #[default]
+    TowardMid,
+    /// `TowardMid` unless that would lock or cross the coarse bests, then
+    /// away from the mid on both sides, which always leaves at least one
+    /// coarse tick between them.
+    ///
+    /// NOTE: when it kicks in the coarse spread is wider than the real one,
+    /// the display errs pessimistic rather than show a crossed book
+    NonCrossing,
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    /// levels of one side merged onto coarse ticks `coarse_ticks` fine ticks
+    /// wide, best first. coarse ticks are multiples of `coarse_ticks` and
+    /// stay in fine tick units, so prices still come from `tick_decimals`
+    pub fn coarsen(
+        &self,
+        side: Side,
+        coarse_ticks: u32,
+        rounding: CoarseRounding,
+    ) -> Vec<TickLevel<T>> {
+        assert!(coarse_ticks > 0, "coarse ticks must span at least one tick");
+        let width = coarse_ticks as u64;
+
+        let away = rounding == CoarseRounding::NonCrossing && self.coarse_bests_cross(width);
+        let up = match side {
+            Side::Ask => away,
+            Side::Bid => !away,
+        };
+
+        let mut levels: Vec<TickLevel<T>> = Vec::new();
+        for (tick, size) in self.side_entries(side) {
+            let tick = round_tick(tick, width, up);
+            // rounding keeps best first order, equal coarse ticks are adjacent
+            match levels.last_mut() {
+                Some(last) if last.tick == tick => last.size += size,
+                _ => levels.push(TickLevel { tick, size }),
+            }
+        }
+        levels
+    }
+
+    /// whether rounding toward the mid locks or crosses the coarse bests
+    fn coarse_bests_cross(&self, width: u64) -> bool {
+        let (Some(ask), Some(bid)) = (self.best_ask_tick(), self.best_bid_tick()) else {
+            return false;
+        };
+        round_tick(ask, width, false) <= round_tick(bid, width, true)
+    }
+}
+
+/// `tick` onto a multiple of `width`, rounding up or down
+fn round_tick<T: Tick>(tick: T, width: u64, up: bool) -> T {
+    let tick = tick.to_u64();
+    let down = tick / width * width;
+    let rounded = if up && down != tick {
+        down.checked_add(width).and_then(T::try_from_u64)
+    } else {
+        None
+    };
+
+    // a coarse tick past `T::MAX` stays on the one below
+    rounded
+        .or_else(|| T::try_from_u64(down))
+        .expect("rounding down never grows the tick")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CoarseRounding, OrderBook, Side, TickLevel, TickUpdate};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn non_crossing_guard() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(105, 1.0), tl(108, 2.0), tl(112, 3.0)],
+            bids: vec![tl(104, 4.0), tl(101, 5.0), tl(95, 6.0)],
+        });
+        println!("{book:#?}");
+
+        // a one tick spread straddling 10 tick buckets crosses when naive
+        let asks = book.coarsen(Side::Ask, 10, CoarseRounding::TowardMid);
+        let bids = book.coarsen(Side::Bid, 10, CoarseRounding::TowardMid);
+        assert_eq!(asks, vec![tl(100, 3.0), tl(110, 3.0)]);
+        assert_eq!(bids, vec![tl(110, 9.0), tl(100, 6.0)]);
+        assert!(asks[0].tick < bids[0].tick);
+
+        let asks = book.coarsen(Side::Ask, 10, CoarseRounding::NonCrossing);
+        let bids = book.coarsen(Side::Bid, 10, CoarseRounding::NonCrossing);
+        assert_eq!(asks, vec![tl(110, 3.0), tl(120, 3.0)]);
+        assert_eq!(bids, vec![tl(100, 9.0), tl(90, 6.0)]);
+        assert_eq!(asks[0].tick - bids[0].tick, 10);
+
+        // sizes are only regrouped
+        let total = |levels: Vec<TickLevel>| levels.iter().map(|l| l.size).sum::<f64>();
+        assert_eq!(
+            total(book.coarsen(Side::Ask, 10, CoarseRounding::NonCrossing)),
+            6.0
+        );
+    }
+
+    #[test]
+    fn guard_idle_on_wide_books() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(121, 1.0), tl(135, 2.0)],
+            bids: vec![tl(99, 4.0), tl(90, 5.0)],
+        });
+
+        for side in [Side::Ask, Side::Bid] {
+            assert_eq!(
+                book.coarsen(side, 10, CoarseRounding::NonCrossing),
+                book.coarsen(side, 10, CoarseRounding::TowardMid)
+            );
+        }
+        assert_eq!(
+            book.coarsen(Side::Ask, 10, CoarseRounding::TowardMid),
+            vec![tl(120, 1.0), tl(130, 2.0)]
+        );
+        assert_eq!(
+            book.coarsen(Side::Bid, 1, CoarseRounding::TowardMid),
+            vec![tl(99, 4.0), tl(90, 5.0)]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use crate::{OrderBook, TickLevel, tick::Decimals, tick::Tick};
+
+/// Error when books can't be consolidated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsolidateError {
+    /// nothing to consolidate, the decimals are unknown
+    NoBooks,
+    /// a book is priced at other decimals than the first
+    DecimalsMismatch { expected: Decimals, found: Decimals },
+}
+
+impl std::fmt::Display for ConsolidateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsolidateError::NoBooks => f.write_str("no books to consolidate"),
+            ConsolidateError::DecimalsMismatch { expected, found } => {
+                write!(f, "book at {found} can't consolidate into {expected}")
+            }
+        }
+    }
+}
+
+impl<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T: Tick>
+    OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>
+{
+    /// consolidated book across venues: every tick holds the summed size of
+    /// that tick in all `books`, which must share decimals.
+    ///
+    /// venue sequence ids don't compare, the result starts at sequence 0.
+    /// it may be crossed when the venues are
+    pub fn consolidate(books: &[&Self]) -> Result<Self, ConsolidateError> {
+        let tick_decimals = books
+            .first()
+            .ok_or(ConsolidateError::NoBooks)?
+            .tick_decimals;
+
+        let mut asks = BTreeMap::new();
+        let mut bids = BTreeMap::new();
+        for book in books {
+            if book.tick_decimals != tick_decimals {
+                return Err(ConsolidateError::DecimalsMismatch {
+                    expected: tick_decimals,
+                    found: book.tick_decimals,
+                });
+            }
+
+            for (tick, size) in book.ask_entries() {
+                *asks.entry(tick).or_insert(0.0) += size;
+            }
+            for (tick, size) in book.bid_entries() {
+                *bids.entry(tick).or_insert(0.0) += size;
+            }
+        }
+
+        let level = |(tick, size)| TickLevel { tick, size };
+        let asks: Vec<_> = asks.into_iter().map(level).collect();
+        let bids: Vec<_> = bids.into_iter().rev().map(level).collect();
+
+        Ok(Self::from_levels(tick_decimals, 0, &asks, &bids))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TickUpdate;
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn consolidate() {
+        let mut venue_a: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let mut venue_b: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = venue_a.process_tick_update(&TickUpdate {
+            sequence_id: 10,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0)],
+        });
+        let _ = venue_b.process_tick_update(&TickUpdate {
+            sequence_id: 900,
+            timestamp: None,
+            asks: vec![tl(102, 1.0), tl(103, 2.0)],
+            bids: vec![tl(100, 3.0), tl(98, 4.0), tl(80, 5.0)],
+        });
+
+        let book = OrderBook::consolidate(&[&venue_a, &venue_b]).unwrap();
+
+        println!("{book}");
+
+        assert_eq!(book.sequence_id(), 0);
+        assert_eq!(
+            book.ask_entries().collect::<Vec<_>>(),
+            vec![(101, 5.0), (102, 16.0), (103, 2.0), (110, 35.0)]
+        );
+        assert_eq!(
+            book.bid_entries().collect::<Vec<_>>(),
+            vec![(100, 3.0), (99, 10.0), (98, 24.0), (80, 5.0)]
+        );
+    }
+
+    #[test]
+    fn consolidate_rejects_mixed_decimals() {
+        let cents: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let mills: OrderBook<4, 1> = OrderBook::new(3u8.try_into().unwrap());
+
+        assert_eq!(
+            OrderBook::consolidate(&[&cents, &mills]).unwrap_err(),
+            ConsolidateError::DecimalsMismatch {
+                expected: 2u8.try_into().unwrap(),
+                found: 3u8.try_into().unwrap(),
+            }
+        );
+        assert_eq!(
+            OrderBook::<4, 1>::consolidate(&[]).unwrap_err(),
+            ConsolidateError::NoBooks
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Side, populated};
+
+/// a single change an update made to the book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookEvent<T = u32> {
+    LevelAdded {
+        side: Side,
+        tick: T,
+        size: f64,
+    },
+    /// `size` is what the level held
+    LevelRemoved {
+        side: Side,
+        tick: T,
+        size: f64,
+    },
+    LevelResized {
+        side: Side,
+        tick: T,
+        old_size: f64,
+        size: f64,
+    },
+    /// cache window moved, reported once per side after the side's levels
+    Rebalanced {
+        side: Side,
+        from_0_tick: T,
+        to_0_tick: T,
+    },
+    /// best tick moved, `None` when the side went empty. reported last
+    BestChanged {
+        side: Side,
+        tick: Option<T>,
+    },
+}
+
+/// receives the events of `process_tick_update_with_events` as they happen
+pub trait EventSink<T = u32> {
+    fn event(&mut self, event: BookEvent<T>);
+}
+
+impl<T> EventSink<T> for Vec<BookEvent<T>> {
+    fn event(&mut self, event: BookEvent<T>) {
+        self.push(event);
+    }
+}
+
+/// hooks the update pipeline reports through. every hook defaults to a no-op
+/// so the plain update path compiles them away
+pub(crate) trait Observer<T> {
+    /// whether `event` does anything, gates capturing state to diff against
+    const EVENTS: bool = false;
+
+    #[inline(always)]
+    fn evicted(&mut self, _side: Side, _tick: T) {}
+
+    #[inline(always)]
+    fn event(&mut self, _event: impl FnOnce() -> BookEvent<T>) {}
+}
+
+impl<T> Observer<T> for () {}
+
+/// levels a rebalance moved from the cache into the heap
+impl<T> Observer<T> for Vec<(Side, T)> {
+    fn evicted(&mut self, side: Side, tick: T) {
+        self.push((side, tick));
+    }
+}
+
+pub(crate) struct Events<'a, S>(pub(crate) &'a mut S);
+
+impl<T, S: EventSink<T>> Observer<T> for Events<'_, S> {
+    const EVENTS: bool = true;
+
+    #[inline]
+    fn event(&mut self, event: impl FnOnce() -> BookEvent<T>) {
+        self.0.event(event());
+    }
+}
+
+/// classifies a level going from `old` to `size`
+#[inline]
+pub(crate) fn level_event<T>(side: Side, tick: T, old: f64, size: f64) -> BookEvent<T> {
+    if !populated(old) {
+        BookEvent::LevelAdded { side, tick, size }
+    } else if !populated(size) {
+        BookEvent::LevelRemoved {
+            side,
+            tick,
+            size: old,
+        }
+    } else {
+        BookEvent::LevelResized {
+            side,
+            tick,
+            old_size: old,
+            size,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    FloatLevel, OrderBook,
+    cache_storage::CacheStorage,
+    heap_alloc::HeapAlloc,
+    tick::{Decimals, Tick},
+};
+
+/// owned, immutable copy of a book to hand to another thread, e.g. in an
+/// `Arc`. the live book keeps updating, the frame doesn't
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookFrame<T: Tick = u32> {
+    sequence_id: u64,
+    tick_decimals: Decimals,
+    /// invariant: sorted lowest to highest tick
+    asks: Box<[(T, f64)]>,
+    /// invariant: sorted highest to lowest tick
+    bids: Box<[(T, f64)]>,
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    /// copies every level into a `BookFrame`
+    pub fn freeze(&self) -> BookFrame<T> {
+        BookFrame {
+            sequence_id: self.sequence_id,
+            tick_decimals: self.tick_decimals,
+            asks: self.ask_entries().collect(),
+            bids: self.bid_entries().collect(),
+        }
+    }
+}
+
+impl<T: Tick> BookFrame<T> {
+    pub fn sequence_id(&self) -> u64 {
+        self.sequence_id
+    }
+
+    pub fn tick_decimals(&self) -> Decimals {
+        self.tick_decimals
+    }
+
+    /// `None` when there are no asks
+    pub fn best_ask(&self) -> Option<FloatLevel> {
+        self.asks.first().map(|&level| self.to_float(level))
+    }
+
+    /// `None` when there are no bids
+    pub fn best_bid(&self) -> Option<FloatLevel> {
+        self.bids.first().map(|&level| self.to_float(level))
+    }
+
+    pub fn best_ask_tick(&self) -> Option<T> {
+        self.asks.first().map(|&(tick, _)| tick)
+    }
+
+    pub fn best_bid_tick(&self) -> Option<T> {
+        self.bids.first().map(|&(tick, _)| tick)
+    }
+
+    /// halfway between best bid and best ask, `None` when a side is empty
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.best_ask()?.price + self.best_bid()?.price) / 2.0)
+    }
+
+    /// raw `(tick, size)` asks, lowest to highest
+    pub fn ask_entries(&self) -> &[(T, f64)] {
+        &self.asks
+    }
+
+    /// raw `(tick, size)` bids, highest to lowest
+    pub fn bid_entries(&self) -> &[(T, f64)] {
+        &self.bids
+    }
+
+    /// lowest to highest
+    pub fn asks(&self) -> impl DoubleEndedIterator<Item = FloatLevel> + '_ {
+        self.asks.iter().map(|&level| self.to_float(level))
+    }
+
+    /// highest to lowest
+    pub fn bids(&self) -> impl DoubleEndedIterator<Item = FloatLevel> + '_ {
+        self.bids.iter().map(|&level| self.to_float(level))
+    }
+
+    fn to_float(&self, (tick, size): (T, f64)) -> FloatLevel {
+        FloatLevel {
+            price: self.tick_decimals.tick_to_f64(tick),
+            size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{OrderBook, TickLevel, TickUpdate};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn frozen_frame_is_detached() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(90, 40.0)],
+        });
+
+        let frame = Arc::new(book.freeze());
+        println!("{frame:#?}");
+        assert_eq!(frame.best_ask(), book.best_ask());
+        assert_eq!(frame.best_bid(), book.best_bid());
+        assert_eq!(frame.mid_price(), book.mid_price());
+        assert!(frame.asks().eq(book.asks()));
+        assert!(frame.bids().eq(book.bids()));
+        assert_eq!(frame.ask_entries(), [(101, 5.0), (102, 15.0), (110, 35.0)]);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![tl(100, 1.0)],
+            bids: vec![tl(99, 0.0)],
+        });
+
+        let reader = std::thread::spawn({
+            let frame = Arc::clone(&frame);
+            move || {
+                (
+                    frame.sequence_id(),
+                    frame.best_ask_tick(),
+                    frame.best_bid_tick(),
+                )
+            }
+        });
+        assert_eq!(reader.join().unwrap(), (1, Some(101), Some(99)));
+        assert_ne!(frame.best_ask(), book.best_ask());
+    }
+}
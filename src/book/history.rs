@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use crate::{BookSnapshot, OrderBook, TickUpdate, UpdateOutcome, tick::Tick};
+
+/// book that keeps snapshots of its last `capacity` states for debugging.
+///
+/// NOTE: every update copies every level of the book into a snapshot, so an
+/// update costs O(levels) on top of the book update. once the ring is full
+/// the oldest snapshot's buffers are reused, so steady state only allocates
+/// when the book grows
+#[derive(Debug, Clone)]
+pub struct HistoryBook<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T: Tick = u32> {
+    book: OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>,
+    history: VecDeque<BookSnapshot<T>>,
+    capacity: usize,
+}
+
+impl<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T: Tick>
+    HistoryBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>
+{
+    /// starts recording from the current state of `book`
+    pub fn new(book: OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>, capacity: usize) -> Self {
+        let mut history = VecDeque::with_capacity(capacity);
+        if capacity > 0 {
+            history.push_back(book.snapshot());
+        }
+
+        Self {
+            book,
+            history,
+            capacity,
+        }
+    }
+
+    pub fn book(&self) -> &OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T> {
+        &self.book
+    }
+
+    pub fn into_book(self) -> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T> {
+        self.book
+    }
+
+    pub fn process_tick_update(&mut self, update: &TickUpdate<T>) -> UpdateOutcome {
+        let outcome = self.book.process_tick_update(update);
+
+        if self.capacity == 0 {
+            return outcome;
+        }
+
+        let mut snapshot = if self.history.len() == self.capacity {
+            self.history.pop_front().unwrap_or_default()
+        } else {
+            BookSnapshot::default()
+        };
+        snapshot.sequence_id = self.book.sequence_id();
+        snapshot.asks.clear();
+        snapshot.asks.extend(self.book.ask_ticks());
+        snapshot.bids.clear();
+        snapshot.bids.extend(self.book.bid_ticks());
+        self.history.push_back(snapshot);
+
+        outcome
+    }
+
+    /// book state right after the update with sequence id `seq` was applied.
+    /// the most recent one wins if a sequence id repeats
+    pub fn at_sequence(&self, seq: u64) -> Option<&BookSnapshot<T>> {
+        self.history.iter().rev().find(|s| s.sequence_id == seq)
+    }
+
+    /// recorded snapshots, oldest first
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &BookSnapshot<T>> {
+        self.history.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HistoryBook;
+    use crate::{OrderBook, TickLevel, TickUpdate};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn at_sequence() {
+        let book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let mut book = HistoryBook::new(book, 3);
+
+        for seq in 1..=5 {
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: seq,
+                timestamp: None,
+                asks: vec![tl(100 + seq as u32, seq as f64)],
+                bids: vec![tl(90, seq as f64)],
+            });
+        }
+
+        println!("{:#?}", book.history().collect::<Vec<_>>());
+
+        // only the last 3 are kept
+        assert!(book.at_sequence(0).is_none());
+        assert!(book.at_sequence(2).is_none());
+
+        let past = book.at_sequence(3).unwrap();
+        assert_eq!(
+            past.asks
+                .iter()
+                .map(|l| (l.tick, l.size))
+                .collect::<Vec<_>>(),
+            vec![(101, 1.0), (102, 2.0), (103, 3.0)]
+        );
+        assert_eq!(
+            past.bids
+                .iter()
+                .map(|l| (l.tick, l.size))
+                .collect::<Vec<_>>(),
+            vec![(90, 3.0)]
+        );
+
+        assert_eq!(book.at_sequence(5).unwrap(), &book.book().snapshot());
+    }
+}
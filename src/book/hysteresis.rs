@@ -0,0 +1,173 @@
+use crate::{OrderBook, TickUpdate, UpdateOutcome, tick::Tick};
+
+/// book with a "sticky" best for signals that shouldn't react to a flickering
+/// top of book.
+///
+/// the raw best is the book's own best. the confirmed best follows it, except
+/// that a move of exactly one tick is only confirmed once the raw best has
+/// sat at the new tick for `hold` consecutive updates. moves of more than one
+/// tick, and a side emptying or filling, are confirmed right away.
+///
+/// NOTE: the confirmed best is a tick, not a level. while a flicker is held
+/// back the confirmed tick may have no size left in the book
+#[derive(Debug, Clone)]
+pub struct HysteresisBook<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T: Tick = u32> {
+    book: OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>,
+    hold: u32,
+    ask: Sticky<T>,
+    bid: Sticky<T>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sticky<T> {
+    confirmed: Option<T>,
+    /// raw best one tick off the confirmed one, and for how many updates
+    pending: Option<(T, u32)>,
+}
+
+impl<T: Tick> Sticky<T> {
+    fn new(best: Option<T>) -> Self {
+        Self {
+            confirmed: best,
+            pending: None,
+        }
+    }
+
+    fn observe(&mut self, raw: Option<T>, hold: u32) {
+        let (Some(confirmed), Some(raw)) = (self.confirmed, raw) else {
+            self.confirmed = raw;
+            self.pending = None;
+            return;
+        };
+
+        let distance = if raw > confirmed {
+            raw - confirmed
+        } else {
+            confirmed - raw
+        };
+        if distance != T::from(1) {
+            // no move, or a big one
+            self.confirmed = Some(raw);
+            self.pending = None;
+            return;
+        }
+
+        let held = match self.pending {
+            Some((tick, held)) if tick == raw => held + 1,
+            _ => 1,
+        };
+        if held >= hold {
+            self.confirmed = Some(raw);
+            self.pending = None;
+        } else {
+            self.pending = Some((raw, held));
+        }
+    }
+}
+
+impl<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T: Tick>
+    HysteresisBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>
+{
+    /// confirms the current best of `book`. `hold` is the number of
+    /// consecutive updates a one tick move must last to be confirmed, 0 and 1
+    /// both confirm it on the update it shows up in
+    pub fn new(book: OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>, hold: u32) -> Self {
+        Self {
+            ask: Sticky::new(book.best_ask_tick()),
+            bid: Sticky::new(book.best_bid_tick()),
+            book,
+            hold,
+        }
+    }
+
+    pub fn book(&self) -> &OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T> {
+        &self.book
+    }
+
+    pub fn into_book(self) -> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T> {
+        self.book
+    }
+
+    /// the outcome is the raw book's, a held back move still reports `Changed`
+    pub fn process_tick_update(&mut self, update: &TickUpdate<T>) -> UpdateOutcome {
+        let outcome = self.book.process_tick_update(update);
+        self.ask.observe(self.book.best_ask_tick(), self.hold);
+        self.bid.observe(self.book.best_bid_tick(), self.hold);
+        outcome
+    }
+
+    /// `None` when there are no asks
+    pub fn confirmed_best_ask(&self) -> Option<T> {
+        self.ask.confirmed
+    }
+
+    /// `None` when there are no bids
+    pub fn confirmed_best_bid(&self) -> Option<T> {
+        self.bid.confirmed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HysteresisBook;
+    use crate::{OrderBook, TickLevel, TickUpdate};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    fn update(sequence_id: u64, asks: Vec<TickLevel>, bids: Vec<TickLevel>) -> TickUpdate {
+        TickUpdate {
+            sequence_id,
+            timestamp: None,
+            asks,
+            bids,
+        }
+    }
+
+    #[test]
+    fn flicker_held_back() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&update(
+            0,
+            vec![tl(102, 5.0), tl(103, 5.0)],
+            vec![tl(99, 5.0), tl(98, 5.0)],
+        ));
+        let mut book = HysteresisBook::new(book, 3);
+
+        // one tick flicker in and out
+        let _ = book.process_tick_update(&update(1, vec![], vec![tl(100, 1.0)]));
+        assert_eq!(book.book().best_bid_tick(), Some(100));
+        assert_eq!(book.confirmed_best_bid(), Some(99));
+        let _ = book.process_tick_update(&update(2, vec![], vec![tl(100, 0.0)]));
+        assert_eq!(book.confirmed_best_bid(), Some(99));
+
+        // same for a one tick drop
+        let _ = book.process_tick_update(&update(3, vec![], vec![tl(99, 0.0)]));
+        let _ = book.process_tick_update(&update(4, vec![], vec![tl(99, 2.0)]));
+        assert_eq!(book.confirmed_best_bid(), Some(99));
+
+        // sustained for 3 updates
+        for seq in 5..8 {
+            assert_eq!(book.confirmed_best_bid(), Some(99));
+            let _ = book.process_tick_update(&update(seq, vec![tl(103, 1.0)], vec![tl(100, 1.0)]));
+        }
+        println!("{:#?}", book.book());
+        assert_eq!(book.confirmed_best_bid(), Some(100));
+        assert_eq!(book.confirmed_best_ask(), Some(102));
+    }
+
+    #[test]
+    fn large_moves_confirmed() {
+        let mut book: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&update(0, vec![tl(102, 5.0)], vec![tl(99, 5.0)]));
+        let mut book = HysteresisBook::new(book, 3);
+
+        let _ = book.process_tick_update(&update(1, vec![tl(100, 1.0)], vec![tl(97, 1.0)]));
+        assert_eq!(book.confirmed_best_ask(), Some(100));
+        assert_eq!(book.confirmed_best_bid(), Some(99));
+
+        let _ = book.process_tick_update(&update(2, vec![tl(100, 0.0), tl(102, 0.0)], vec![]));
+        assert_eq!(book.confirmed_best_ask(), None);
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::btree_map,
+    iter::{Enumerate, FusedIterator},
+    slice,
+};
+
+use crate::{
+    FloatLevel, OrderBook,
+    cache_storage::CacheStorage,
+    heap_alloc::HeapAlloc,
+    populated,
+    tick::{Decimals, Tick},
+};
+
+/// asks lowest to highest, see `OrderBook::asks`
+#[derive(Debug, Clone)]
+pub struct AskIter<'a, T: Tick = u32>(Levels<'a, T, true>);
+
+/// bids highest to lowest, see `OrderBook::bids`
+#[derive(Debug, Clone)]
+pub struct BidIter<'a, T: Tick = u32>(Levels<'a, T, false>);
+
+/// populated cache slots from the best, then the heap. `ASK` picks the
+/// direction ticks grow in from the anchor and the heap is walked in
+#[derive(Debug, Clone)]
+struct Levels<'a, T: Tick, const ASK: bool> {
+    /// from the best slot on
+    cache: Enumerate<slice::Iter<'a, f64>>,
+    heap: btree_map::Iter<'a, T, (f64, u64)>,
+    prices: Prices<'a, T, ASK>,
+}
+
+/// cache slot index or heap tick to a price
+#[derive(Debug, Clone, Copy)]
+struct Prices<'a, T: Tick, const ASK: bool> {
+    /// tick of the best cache slot
+    anchor: T,
+    tick_decimals: Decimals,
+    #[cfg(feature = "price-memo")]
+    memo: Option<&'a [f64]>,
+    #[cfg(not(feature = "price-memo"))]
+    memo: std::marker::PhantomData<&'a [f64]>,
+}
+
+impl<T: Tick, const ASK: bool> Prices<'_, T, ASK> {
+    #[inline]
+    fn cache_level(&self, i: usize, size: f64) -> FloatLevel {
+        #[cfg(feature = "price-memo")]
+        if let Some(memo) = self.memo {
+            return FloatLevel {
+                price: memo[i],
+                size,
+            };
+        }
+
+        let tick = if ASK {
+            self.anchor + T::from_index(i)
+        } else {
+            self.anchor - T::from_index(i)
+        };
+        FloatLevel {
+            price: self.tick_decimals.tick_to_f64(tick),
+            size,
+        }
+    }
+
+    #[inline]
+    fn heap_level(&self, (tick, (size, _)): (&T, &(f64, u64))) -> FloatLevel {
+        FloatLevel {
+            price: self.tick_decimals.tick_to_f64(*tick),
+            size: *size,
+        }
+    }
+}
+
+impl<T: Tick, const ASK: bool> Iterator for Levels<'_, T, ASK> {
+    type Item = FloatLevel;
+
+    #[inline]
+    fn next(&mut self) -> Option<FloatLevel> {
+        for (i, size) in self.cache.by_ref() {
+            if populated(*size) {
+                return Some(self.prices.cache_level(i, *size));
+            }
+        }
+
+        let level = if ASK {
+            self.heap.next()
+        } else {
+            self.heap.next_back()
+        };
+        level.map(|level| self.prices.heap_level(level))
+    }
+
+    /// internal iteration for `sum`, `for_each` and friends, skips the
+    /// per-item cache/heap switch of `next`
+    #[inline]
+    fn fold<B, F>(self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, FloatLevel) -> B,
+    {
+        let prices = self.prices;
+        let acc = self
+            .cache
+            .filter_map(|(i, size)| {
+                if !populated(*size) {
+                    None
+                } else {
+                    Some(prices.cache_level(i, *size))
+                }
+            })
+            .fold(init, &mut f);
+        if ASK {
+            self.heap
+                .fold(acc, |acc, level| f(acc, prices.heap_level(level)))
+        } else {
+            self.heap
+                .rev()
+                .fold(acc, |acc, level| f(acc, prices.heap_level(level)))
+        }
+    }
+
+    /// exact. scans what's left of the cache window, the heap length is known
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let cache = self
+            .cache
+            .clone()
+            .filter(|(_, size)| populated(**size))
+            .count();
+        let n = cache + self.heap.len();
+        (n, Some(n))
+    }
+}
+
+impl<T: Tick, const ASK: bool> DoubleEndedIterator for Levels<'_, T, ASK> {
+    #[inline]
+    fn next_back(&mut self) -> Option<FloatLevel> {
+        let level = if ASK {
+            self.heap.next_back()
+        } else {
+            self.heap.next()
+        };
+        if let Some(level) = level {
+            return Some(self.prices.heap_level(level));
+        }
+
+        while let Some((i, size)) = self.cache.next_back() {
+            if populated(*size) {
+                return Some(self.prices.cache_level(i, *size));
+            }
+        }
+        None
+    }
+}
+
+macro_rules! forward_iter {
+    ($name:ident) => {
+        impl<T: Tick> Iterator for $name<'_, T> {
+            type Item = FloatLevel;
+
+            #[inline]
+            fn next(&mut self) -> Option<FloatLevel> {
+                self.0.next()
+            }
+
+            #[inline]
+            fn fold<B, F>(self, init: B, f: F) -> B
+            where
+                F: FnMut(B, FloatLevel) -> B,
+            {
+                self.0.fold(init, f)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
+        }
+
+        impl<T: Tick> DoubleEndedIterator for $name<'_, T> {
+            #[inline]
+            fn next_back(&mut self) -> Option<FloatLevel> {
+                self.0.next_back()
+            }
+        }
+
+        impl<T: Tick> ExactSizeIterator for $name<'_, T> {}
+
+        impl<T: Tick> FusedIterator for $name<'_, T> {}
+    };
+}
+
+forward_iter!(AskIter);
+forward_iter!(BidIter);
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    /// lowest to highest. `len` is exact but scans the cache window
+    ///
+    /// with `price-memo` cache prices come from a memo filled on first use
+    /// after the window moved
+    pub fn asks(&self) -> AskIter<'_, T> {
+        let best_i = self.best_ask_i as usize;
+        AskIter(Levels {
+            cache: self
+                .asks
+                .get(best_i..)
+                .unwrap_or_default()
+                .iter()
+                .enumerate(),
+            heap: self.asks_heap.iter(),
+            prices: Prices {
+                anchor: self.asks_0_tick.saturating_add(T::from_index(best_i)),
+                tick_decimals: self.tick_decimals,
+                #[cfg(feature = "price-memo")]
+                memo: self
+                    .ask_prices
+                    .get(self.asks_0_tick, || {
+                        (0..CACHE_SLOTS)
+                            .map(|i| {
+                                self.tick_decimals
+                                    .tick_to_f64(self.asks_0_tick.saturating_add(T::from_index(i)))
+                            })
+                            .collect()
+                    })
+                    .and_then(|memo| memo.get(best_i..)),
+                #[cfg(not(feature = "price-memo"))]
+                memo: std::marker::PhantomData,
+            },
+        })
+    }
+
+    /// highest to lowest, see `asks`
+    pub fn bids(&self) -> BidIter<'_, T> {
+        let best_i = self.best_bid_i as usize;
+        BidIter(Levels {
+            cache: self
+                .bids
+                .get(best_i..)
+                .unwrap_or_default()
+                .iter()
+                .enumerate(),
+            heap: self.bids_heap.iter(),
+            prices: Prices {
+                anchor: self.bids_0_tick.saturating_sub(T::from_index(best_i)),
+                tick_decimals: self.tick_decimals,
+                #[cfg(feature = "price-memo")]
+                memo: self
+                    .bid_prices
+                    .get(self.bids_0_tick, || {
+                        (0..CACHE_SLOTS)
+                            .map(|i| {
+                                self.tick_decimals
+                                    .tick_to_f64(self.bids_0_tick.saturating_sub(T::from_index(i)))
+                            })
+                            .collect()
+                    })
+                    .and_then(|memo| memo.get(best_i..)),
+                #[cfg(not(feature = "price-memo"))]
+                memo: std::marker::PhantomData,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AskIter, BidIter, OrderBook, TickLevel, TickUpdate};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    struct Top<'a> {
+        asks: AskIter<'a>,
+        bids: BidIter<'a>,
+    }
+
+    #[test]
+    fn exact_size() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        assert_eq!(book.asks().len(), 0);
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(103, 15.0), tl(110, 35.0), tl(120, 1.0)],
+            bids: vec![tl(99, 10.0), tl(90, 40.0)],
+        });
+        println!("{book:#?}");
+
+        let mut top = Top {
+            asks: book.asks(),
+            bids: book.bids(),
+        };
+        assert_eq!(top.asks.size_hint(), (4, Some(4)));
+        assert_eq!(top.bids.len(), 2);
+
+        // shrinks from both ends, across the cache and heap boundary
+        assert_eq!(top.asks.next().unwrap().price, 1.01);
+        assert_eq!(top.asks.next_back().unwrap().price, 1.2);
+        assert_eq!(top.asks.len(), 2);
+        assert_eq!(top.asks.next_back().unwrap().price, 1.1);
+        assert_eq!(top.asks.next_back().unwrap().price, 1.03);
+        assert_eq!(top.asks.len(), 0);
+        assert!(top.asks.next().is_none());
+
+        assert_eq!(top.bids.next().unwrap().price, 0.99);
+        assert_eq!(top.bids.len(), 1);
+        assert_eq!(top.bids.next().unwrap().price, 0.9);
+        assert!(top.bids.next().is_none());
+
+        assert!(
+            book.asks()
+                .rev()
+                .map(|l| l.price)
+                .eq([1.2, 1.1, 1.03, 1.01])
+        );
+        assert!(book.bids().rev().map(|l| l.price).eq([0.9, 0.99]));
+    }
+}
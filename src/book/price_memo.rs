@@ -0,0 +1,38 @@
+//! NOTE: measured with `benches/render.rs` (128 slots, 90 levels a side) the
+//! memo doesn't beat converting each tick: ~250ns either way, within noise.
+//! a tick conversion is one int to float cast and a multiply, about the cost
+//! of the extra load from the memo. kept behind `price-memo` for targets where
+//! the multiply is dearer, off by default
+use std::sync::OnceLock;
+
+/// lazily computed prices of every cache slot, valid for the anchor tick they
+/// were computed at. a `OnceLock` so the book stays `Sync` with the feature on
+#[derive(Clone, Default)]
+pub(crate) struct PriceMemo<T> {
+    prices: OnceLock<(T, Box<[f64]>)>,
+}
+
+// derived state, left out so books with equal levels debug print the same
+impl<T> std::fmt::Debug for PriceMemo<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PriceMemo")
+    }
+}
+
+impl<T: PartialEq + Copy> PriceMemo<T> {
+    /// `None` when the memo was filled for a different anchor and hasn't been
+    /// invalidated yet
+    #[inline]
+    pub(crate) fn get(&self, anchor: T, init: impl FnOnce() -> Box<[f64]>) -> Option<&[f64]> {
+        let (memo_anchor, prices) = self.prices.get_or_init(|| (anchor, init()));
+        (*memo_anchor == anchor).then_some(&**prices)
+    }
+
+    /// drops the memo if the window moved away from its anchor
+    #[inline]
+    pub(crate) fn invalidate(&mut self, anchor: T) {
+        if self.prices.get().is_some_and(|(a, _)| *a != anchor) {
+            self.prices.take();
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    cmp::Ordering,
+    io::{self, Write},
+};
+
+use crate::{
+    OrderBook, Side, TickLevel, TickUpdate, cache_storage::CacheStorage, heap_alloc::HeapAlloc,
+    tick::Tick,
+};
+
+/// owned copy of every level in a book
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookSnapshot<T: Tick = u32> {
+    pub sequence_id: u64,
+    /// invariant: sorted lowest to highest price
+    pub asks: Vec<TickLevel<T>>,
+    /// invariant: sorted highest to lowest price
+    pub bids: Vec<TickLevel<T>>,
+}
+
+/// first level where the book differs from an expected level list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mismatch<T: Tick = u32> {
+    pub side: Side,
+    /// levels from the best
+    pub depth: usize,
+    /// `None` when the expected list ran out first
+    pub expected: Option<TickLevel<T>>,
+    /// `None` when the book ran out first
+    pub actual: Option<TickLevel<T>>,
+}
+
+impl<T: Tick> std::fmt::Display for Mismatch<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = |l: &Option<TickLevel<T>>| match l {
+            Some(l) => format!("{} @ tick {}", l.size, l.tick),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "{:?} level {} mismatch: expected {}, book has {}",
+            self.side,
+            self.depth,
+            level(&self.expected),
+            level(&self.actual)
+        )
+    }
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    pub fn snapshot(&self) -> BookSnapshot<T> {
+        BookSnapshot {
+            sequence_id: self.sequence_id,
+            asks: self.ask_ticks().collect(),
+            bids: self.bid_ticks().collect(),
+        }
+    }
+
+    /// `snapshot` into caller owned buffers, cleared first. keeps their
+    /// capacity so snapshotting every update stops allocating once warm
+    pub fn snapshot_into(&self, asks: &mut Vec<TickLevel<T>>, bids: &mut Vec<TickLevel<T>>) {
+        asks.clear();
+        asks.extend(self.ask_ticks());
+        bids.clear();
+        bids.extend(self.bid_ticks());
+    }
+
+    /// top `depth` levels of each side as
+    /// `{"seq":..,"bids":[[price,size],..],"asks":[[price,size],..]}`, best
+    /// first, written straight to `w`. prices are printed with the book's
+    /// decimals
+    pub fn write_top_json<W: Write>(&self, w: &mut W, depth: usize) -> io::Result<()> {
+        let precision = self.tick_decimals.value() as usize;
+        let side = |w: &mut W, levels: &mut dyn Iterator<Item = TickLevel<T>>| {
+            w.write_all(b"[")?;
+            for (i, level) in levels.take(depth).enumerate() {
+                if i > 0 {
+                    w.write_all(b",")?;
+                }
+                let price = self.tick_decimals.tick_to_f64(level.tick);
+                write!(w, "[{price:.precision$},{}]", level.size)?;
+            }
+            w.write_all(b"]")
+        };
+
+        write!(w, "{{\"seq\":{},\"bids\":", self.sequence_id)?;
+        side(w, &mut self.bid_ticks())?;
+        w.write_all(b",\"asks\":")?;
+        side(w, &mut self.ask_ticks())?;
+        w.write_all(b"}")
+    }
+
+    /// checks the book holds exactly `asks` and `bids`, sizes within `eps`
+    ///
+    /// invariant: asks sorted lowest to highest, bids highest to lowest
+    pub fn matches_levels(
+        &self,
+        asks: &[TickLevel<T>],
+        bids: &[TickLevel<T>],
+        eps: f64,
+    ) -> Result<(), Mismatch<T>> {
+        match_side(Side::Ask, asks, self.ask_ticks(), eps)?;
+        match_side(Side::Bid, bids, self.bid_ticks(), eps)
+    }
+
+    /// minimal update that turns a book holding `prior` into this book:
+    /// changed and added levels with their current size, vanished levels
+    /// with size 0.0
+    pub fn diff_since(&self, prior: &BookSnapshot<T>) -> TickUpdate<T> {
+        TickUpdate {
+            sequence_id: self.sequence_id,
+            timestamp: self.last_timestamp,
+            asks: diff_side(&prior.asks, self.ask_entries(), |a, b| a.cmp(&b)),
+            bids: diff_side(&prior.bids, self.bid_entries(), |a, b| b.cmp(&a)),
+        }
+    }
+}
+
+fn match_side<T: Tick>(
+    side: Side,
+    expected: &[TickLevel<T>],
+    mut actual: impl Iterator<Item = TickLevel<T>>,
+    eps: f64,
+) -> Result<(), Mismatch<T>> {
+    let mut expected = expected.iter().copied();
+
+    for depth in 0.. {
+        match (expected.next(), actual.next()) {
+            (None, None) => return Ok(()),
+            (Some(e), Some(a)) if e.tick == a.tick && (e.size - a.size).abs() <= eps => {}
+            (expected, actual) => {
+                return Err(Mismatch {
+                    side,
+                    depth,
+                    expected,
+                    actual,
+                });
+            }
+        }
+    }
+
+    unreachable!()
+}
+
+/// merges two sides sorted best first by `order`
+fn diff_side<T: Tick>(
+    prior: &[TickLevel<T>],
+    current: impl Iterator<Item = (T, f64)>,
+    order: impl Fn(T, T) -> Ordering,
+) -> Vec<TickLevel<T>> {
+    let mut diff = Vec::new();
+    let mut prior = prior.iter().peekable();
+    let mut current = current.peekable();
+
+    loop {
+        let next = match (prior.peek(), current.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(old), Some((tick, _))) => order(old.tick, *tick),
+        };
+
+        match next {
+            // level vanished
+            Ordering::Less => {
+                let old = prior.next().unwrap();
+                diff.push(TickLevel {
+                    tick: old.tick,
+                    size: 0.0,
+                });
+            }
+            // level appeared
+            Ordering::Greater => {
+                let (tick, size) = current.next().unwrap();
+                diff.push(TickLevel { tick, size });
+            }
+            Ordering::Equal => {
+                let old = prior.next().unwrap();
+                let (tick, size) = current.next().unwrap();
+                if old.size != size {
+                    diff.push(TickLevel { tick, size });
+                }
+            }
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mismatch;
+    use crate::{OrderBook, Side, TickLevel, TickUpdate, UpdateOutcome};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn top_json() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 7,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 0.25), tl(110, 35.0)],
+            bids: vec![tl(100, 10.0), tl(90, 40.0)],
+        });
+
+        let mut out = Vec::new();
+        book.write_top_json(&mut out, 2).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        println!("{json}");
+        assert_eq!(
+            json,
+            r#"{"seq":7,"bids":[[1.00,10],[0.90,40]],"asks":[[1.01,5],[1.02,0.25]]}"#
+        );
+
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let levels = |side: &str| -> Vec<(f64, f64)> {
+            parsed[side]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|l| (l[0].as_f64().unwrap(), l[1].as_f64().unwrap()))
+                .collect()
+        };
+        assert_eq!(parsed["seq"], 7);
+        assert_eq!(levels("bids"), vec![(1.0, 10.0), (0.9, 40.0)]);
+        assert_eq!(levels("asks"), vec![(1.01, 5.0), (1.02, 0.25)]);
+
+        let mut out = Vec::new();
+        OrderBook::<4, 1>::new(2u8.try_into().unwrap())
+            .write_top_json(&mut out, 5)
+            .unwrap();
+        assert_eq!(out, br#"{"seq":0,"bids":[],"asks":[]}"#);
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 3,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(90, 40.0)],
+        });
+
+        let snapshot = book.snapshot();
+
+        assert_eq!(snapshot.sequence_id, 3);
+        assert_eq!(
+            snapshot
+                .asks
+                .iter()
+                .map(|l| (l.tick, l.size))
+                .collect::<Vec<_>>(),
+            vec![(101, 5.0), (110, 35.0)]
+        );
+        assert_eq!(
+            snapshot
+                .bids
+                .iter()
+                .map(|l| (l.tick, l.size))
+                .collect::<Vec<_>>(),
+            vec![(99, 10.0), (90, 40.0)]
+        );
+    }
+
+    #[test]
+    fn snapshot_into_reuses_buffers() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let mut asks = Vec::new();
+        let mut bids = Vec::new();
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(90, 40.0)],
+        });
+        book.snapshot_into(&mut asks, &mut bids);
+        book.snapshot_into(&mut asks, &mut bids);
+
+        let snapshot = book.snapshot();
+        assert_eq!(asks, snapshot.asks);
+        assert_eq!(bids, snapshot.bids);
+        let capacity = (asks.capacity(), bids.capacity());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(102, 0.0)],
+            bids: vec![tl(99, 0.0), tl(90, 0.0)],
+        });
+        book.snapshot_into(&mut asks, &mut bids);
+
+        assert_eq!(asks, vec![tl(101, 5.0), tl(110, 35.0)]);
+        assert!(bids.is_empty());
+        assert_eq!((asks.capacity(), bids.capacity()), capacity);
+    }
+
+    #[test]
+    fn diff_round_trip() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0), tl(111, 45.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0), tl(89, 50.0)],
+        });
+        let prior = book.snapshot();
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![
+                tl(100, 1.0),
+                tl(101, 0.0),
+                tl(110, 36.0),
+                tl(111, 0.0),
+                tl(120, 7.0),
+            ],
+            bids: vec![tl(99, 11.0), tl(98, 0.0), tl(89, 0.0), tl(80, 8.0)],
+        });
+
+        let diff = book.diff_since(&prior);
+
+        assert_eq!(diff.sequence_id, 1);
+        assert_eq!(
+            diff.asks
+                .iter()
+                .map(|l| (l.tick, l.size))
+                .collect::<Vec<_>>(),
+            vec![(100, 1.0), (101, 0.0), (110, 36.0), (111, 0.0), (120, 7.0)]
+        );
+        assert_eq!(
+            diff.bids
+                .iter()
+                .map(|l| (l.tick, l.size))
+                .collect::<Vec<_>>(),
+            vec![(99, 11.0), (98, 0.0), (89, 0.0), (80, 8.0)]
+        );
+
+        let mut restored: OrderBook<4, 1> = OrderBook::from_levels(
+            2u8.try_into().unwrap(),
+            prior.sequence_id,
+            &prior.asks,
+            &prior.bids,
+        );
+        let _ = restored.process_tick_update(&diff);
+
+        assert_eq!(restored.snapshot(), book.snapshot());
+
+        // nothing left to send once caught up
+        let diff = book.diff_since(&restored.snapshot());
+        assert!(diff.asks.is_empty());
+        assert!(diff.bids.is_empty());
+        assert_eq!(
+            restored.process_tick_update(&diff),
+            UpdateOutcome::Unchanged
+        );
+    }
+
+    #[test]
+    fn matches_levels() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let asks = vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)];
+        let bids = vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)];
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: asks.clone(),
+            bids: bids.clone(),
+        });
+
+        assert_eq!(book.matches_levels(&asks, &bids, 1e-9), Ok(()));
+        assert_eq!(
+            book.matches_levels(&asks, &bids[..2], 1e-9)
+                .unwrap_err()
+                .depth,
+            2
+        );
+    }
+
+    #[test]
+    fn matches_levels_size_mismatch() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let asks = vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)];
+        let mut bids = vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)];
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: asks.clone(),
+            bids: bids.clone(),
+        });
+
+        bids[1].size = 21.0;
+        let mismatch = book.matches_levels(&asks, &bids, 1e-9).unwrap_err();
+
+        println!("{mismatch}");
+
+        assert_eq!(
+            mismatch,
+            Mismatch {
+                side: Side::Bid,
+                depth: 1,
+                expected: Some(tl(98, 21.0)),
+                actual: Some(tl(98, 20.0)),
+            }
+        );
+
+        // within tolerance
+        assert_eq!(book.matches_levels(&asks, &bids, 1.0), Ok(()));
+    }
+}
@@ -0,0 +1,301 @@
+//! binary book snapshot, queried in place so a file mmapped from another
+//! process starts serving without a copy or rebuilding the book.
+//!
+//! all integers little endian:
+//!
+//! ```text
+//! [u8; 4] magic "OBS1"
+//! u8  tick decimals
+//! u64 sequence_id
+//! u32 ask count
+//! u32 bid count
+//! per ask, lowest first, u64 tick, f64 size
+//! per bid, highest first, u64 tick, f64 size
+//! ```
+
+use std::{
+    io::{self, Write},
+    marker::PhantomData,
+};
+
+use crate::{
+    FloatLevel, OrderBook,
+    cache_storage::CacheStorage,
+    heap_alloc::HeapAlloc,
+    tick::{DecimalRangeError, Decimals, Tick},
+};
+
+const MAGIC: [u8; 4] = *b"OBS1";
+const HEADER_BYTES: usize = 4 + 1 + 8 + 4 + 4;
+const LEVEL_BYTES: usize = 8 + 8;
+
+/// why bytes aren't a snapshot, see `view_from_bytes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotViewError {
+    /// shorter than the header or not starting with the magic
+    BadHeader,
+    Decimals(DecimalRangeError),
+    /// the level counts in the header don't add up to the byte length
+    Length {
+        expected: usize,
+        found: usize,
+    },
+    /// a tick doesn't fit the view's tick type
+    TickOutOfRange,
+}
+
+impl std::fmt::Display for SnapshotViewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotViewError::BadHeader => f.write_str("not a book snapshot"),
+            SnapshotViewError::Decimals(err) => err.fmt(f),
+            SnapshotViewError::Length { expected, found } => {
+                write!(f, "snapshot should be {expected} bytes, found {found}")
+            }
+            SnapshotViewError::TickOutOfRange => f.write_str("snapshot tick out of range"),
+        }
+    }
+}
+
+/// read only book over snapshot bytes, borrowed as is. see `view_from_bytes`
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotView<'a, T: Tick = u32> {
+    sequence_id: u64,
+    tick_decimals: Decimals,
+    /// invariant: whole levels, lowest to highest tick
+    asks: &'a [u8],
+    /// invariant: whole levels, highest to lowest tick
+    bids: &'a [u8],
+    tick: PhantomData<T>,
+}
+
+/// checks the header, the length and that every tick fits `T`, then views
+/// `bytes` in place. no allocation
+///
+/// NOTE: level order isn't checked, bytes not from `write_snapshot_bytes`
+/// may answer nonsense
+pub fn view_from_bytes<T: Tick>(bytes: &[u8]) -> Result<SnapshotView<'_, T>, SnapshotViewError> {
+    let Some((header, levels)) = bytes.split_first_chunk::<HEADER_BYTES>() else {
+        return Err(SnapshotViewError::BadHeader);
+    };
+    if header[..4] != MAGIC {
+        return Err(SnapshotViewError::BadHeader);
+    }
+
+    let tick_decimals = Decimals::new(header[4]).map_err(SnapshotViewError::Decimals)?;
+    let sequence_id = u64::from_le_bytes(header[5..13].try_into().unwrap());
+    let ask_count = u32::from_le_bytes(header[13..17].try_into().unwrap()) as usize;
+    let bid_count = u32::from_le_bytes(header[17..21].try_into().unwrap()) as usize;
+
+    let expected = HEADER_BYTES + (ask_count + bid_count) * LEVEL_BYTES;
+    if bytes.len() != expected {
+        return Err(SnapshotViewError::Length {
+            expected,
+            found: bytes.len(),
+        });
+    }
+    if levels
+        .chunks_exact(LEVEL_BYTES)
+        .any(|level| T::try_from_u64(decode(level).0).is_none())
+    {
+        return Err(SnapshotViewError::TickOutOfRange);
+    }
+
+    let (asks, bids) = levels.split_at(ask_count * LEVEL_BYTES);
+    Ok(SnapshotView {
+        sequence_id,
+        tick_decimals,
+        asks,
+        bids,
+        tick: PhantomData,
+    })
+}
+
+/// raw tick and size of one level
+#[inline]
+fn decode(level: &[u8]) -> (u64, f64) {
+    let (tick, size) = level.split_at(8);
+    (
+        u64::from_le_bytes(tick.try_into().unwrap()),
+        f64::from_le_bytes(size.try_into().unwrap()),
+    )
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    /// every level in the binary snapshot format, for `view_from_bytes`
+    pub fn write_snapshot_bytes<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&MAGIC)?;
+        w.write_all(&[self.tick_decimals.value()])?;
+        w.write_all(&self.sequence_id.to_le_bytes())?;
+        w.write_all(&(self.asks().len() as u32).to_le_bytes())?;
+        w.write_all(&(self.bids().len() as u32).to_le_bytes())?;
+        for (tick, size) in self.ask_entries().chain(self.bid_entries()) {
+            w.write_all(&tick.to_u64().to_le_bytes())?;
+            w.write_all(&size.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: Tick> SnapshotView<'a, T> {
+    pub fn sequence_id(&self) -> u64 {
+        self.sequence_id
+    }
+
+    pub fn tick_decimals(&self) -> Decimals {
+        self.tick_decimals
+    }
+
+    /// `None` when there are no asks
+    pub fn best_ask(&self) -> Option<FloatLevel> {
+        self.asks().next()
+    }
+
+    /// `None` when there are no bids
+    pub fn best_bid(&self) -> Option<FloatLevel> {
+        self.bids().next()
+    }
+
+    pub fn best_ask_tick(&self) -> Option<T> {
+        self.ask_entries().next().map(|(tick, _)| tick)
+    }
+
+    pub fn best_bid_tick(&self) -> Option<T> {
+        self.bid_entries().next().map(|(tick, _)| tick)
+    }
+
+    /// halfway between best bid and best ask, `None` when a side is empty
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.best_ask()?.price + self.best_bid()?.price) / 2.0)
+    }
+
+    /// raw `(tick, size)` asks, lowest to highest
+    pub fn ask_entries(&self) -> impl ExactSizeIterator<Item = (T, f64)> + 'a {
+        entries(self.asks)
+    }
+
+    /// raw `(tick, size)` bids, highest to lowest
+    pub fn bid_entries(&self) -> impl ExactSizeIterator<Item = (T, f64)> + 'a {
+        entries(self.bids)
+    }
+
+    /// lowest to highest
+    pub fn asks(&self) -> impl ExactSizeIterator<Item = FloatLevel> + 'a {
+        let tick_decimals = self.tick_decimals;
+        self.ask_entries().map(move |(tick, size)| FloatLevel {
+            price: tick_decimals.tick_to_f64(tick),
+            size,
+        })
+    }
+
+    /// highest to lowest
+    pub fn bids(&self) -> impl ExactSizeIterator<Item = FloatLevel> + 'a {
+        let tick_decimals = self.tick_decimals;
+        self.bid_entries().map(move |(tick, size)| FloatLevel {
+            price: tick_decimals.tick_to_f64(tick),
+            size,
+        })
+    }
+}
+
+fn entries<T: Tick>(levels: &[u8]) -> impl ExactSizeIterator<Item = (T, f64)> + '_ {
+    levels.chunks_exact(LEVEL_BYTES).map(|level| {
+        let (tick, size) = decode(level);
+        // ticks were checked to fit when the view was made
+        (T::try_from_u64(tick).unwrap(), size)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SnapshotViewError;
+    use crate::{OrderBook, TickLevel, TickUpdate, view_from_bytes};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn view_borrowed_bytes() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 9,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(90, 40.0)],
+        });
+        println!("{book:#?}");
+
+        let mut bytes = Vec::new();
+        book.write_snapshot_bytes(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 21 + 5 * 16);
+
+        let view = view_from_bytes::<u32>(&bytes).unwrap();
+        assert_eq!(view.sequence_id(), 9);
+        assert_eq!(view.tick_decimals(), book.tick_decimals);
+        assert_eq!(view.best_ask(), book.best_ask());
+        assert_eq!(view.best_bid(), book.best_bid());
+        assert_eq!(view.best_ask_tick(), Some(101));
+        assert_eq!(view.mid_price(), book.mid_price());
+        assert!(view.asks().eq(book.asks()));
+        assert!(view.bids().eq(book.bids()));
+        assert_eq!(view.bid_entries().len(), 2);
+
+        // the view borrows the buffer, it outlives the book
+        drop(book);
+        assert_eq!(view.best_bid_tick(), Some(99));
+
+        let empty = OrderBook::<4, 1>::new(2u8.try_into().unwrap());
+        let mut bytes = Vec::new();
+        empty.write_snapshot_bytes(&mut bytes).unwrap();
+        let view = view_from_bytes::<u32>(&bytes).unwrap();
+        assert_eq!(view.best_ask(), None);
+        assert_eq!(view.mid_price(), None);
+    }
+
+    #[test]
+    fn rejects_bad_bytes() {
+        let mut book: OrderBook<4, 1, u64> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![TickLevel {
+                tick: u32::MAX as u64 + 1,
+                size: 1.0,
+            }],
+            bids: vec![],
+        });
+        let mut bytes = Vec::new();
+        book.write_snapshot_bytes(&mut bytes).unwrap();
+
+        assert!(view_from_bytes::<u64>(&bytes).is_ok());
+        assert_eq!(
+            view_from_bytes::<u32>(&bytes).unwrap_err(),
+            SnapshotViewError::TickOutOfRange
+        );
+        assert_eq!(
+            view_from_bytes::<u64>(&bytes[..bytes.len() - 1]).unwrap_err(),
+            SnapshotViewError::Length {
+                expected: 37,
+                found: 36
+            }
+        );
+        assert_eq!(
+            view_from_bytes::<u64>(&bytes[1..5]).unwrap_err(),
+            SnapshotViewError::BadHeader
+        );
+
+        bytes[4] = 200;
+        assert!(matches!(
+            view_from_bytes::<u64>(&bytes),
+            Err(SnapshotViewError::Decimals(_))
+        ));
+    }
+}
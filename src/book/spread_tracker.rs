@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+
+/// rolling window of the last `capacity` spreads in ticks, for spread
+/// volatility. see `OrderBook::with_spread_tracking`
+///
+/// only updates leaving both sides populated and uncrossed are recorded
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpreadTracker {
+    spreads: VecDeque<f64>,
+    capacity: usize,
+}
+
+impl SpreadTracker {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            spreads: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// records `spread_ticks`, dropping the oldest once full
+    pub fn push(&mut self, spread_ticks: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.spreads.len() == self.capacity {
+            self.spreads.pop_front();
+        }
+        self.spreads.push_back(spread_ticks);
+    }
+
+    /// recorded spreads in ticks, oldest first
+    pub fn spreads(&self) -> impl DoubleEndedIterator<Item = f64> + '_ {
+        self.spreads.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.spreads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spreads.is_empty()
+    }
+
+    /// mean spread in ticks over the window, `None` while empty
+    pub fn mean_spread(&self) -> Option<f64> {
+        if self.spreads.is_empty() {
+            return None;
+        }
+        Some(self.spreads.iter().sum::<f64>() / self.spreads.len() as f64)
+    }
+
+    /// population standard deviation of the spread in ticks over the window,
+    /// `None` while empty
+    pub fn spread_stddev(&self) -> Option<f64> {
+        let mean = self.mean_spread()?;
+        let variance = self
+            .spreads
+            .iter()
+            .map(|spread| (spread - mean) * (spread - mean))
+            .sum::<f64>()
+            / self.spreads.len() as f64;
+        Some(variance.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpreadTracker;
+    use crate::{OrderBook, TickLevel, TickUpdate};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn rolling_window() {
+        let mut tracker = SpreadTracker::new(3);
+        assert_eq!(tracker.mean_spread(), None);
+        assert_eq!(tracker.spread_stddev(), None);
+
+        for spread in [10.0, 2.0, 4.0, 6.0] {
+            tracker.push(spread);
+        }
+        assert_eq!(tracker.spreads().collect::<Vec<_>>(), vec![2.0, 4.0, 6.0]);
+        assert_eq!(tracker.mean_spread(), Some(4.0));
+        assert!((tracker.spread_stddev().unwrap() - (8.0f64 / 3.0).sqrt()).abs() < 1e-12);
+
+        let mut off = SpreadTracker::new(0);
+        off.push(1.0);
+        assert!(off.is_empty());
+    }
+
+    #[test]
+    fn fed_by_updates() {
+        let mut book: OrderBook<8, 2> =
+            OrderBook::new(2u8.try_into().unwrap()).with_spread_tracking(4);
+
+        let tops = [(105, 99), (104, 99), (104, 101), (102, 101), (102, 101)];
+        for (seq, (ask, bid)) in tops.into_iter().enumerate() {
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: seq as u64,
+                timestamp: None,
+                asks: vec![tl(ask, 1.0)],
+                bids: vec![tl(bid, 1.0)],
+            });
+        }
+        println!("{book:#?}");
+
+        // spreads 6, 5, 3, 1, 1, the first fell out of the window
+        let tracker = book.spread_tracker().unwrap();
+        assert_eq!(
+            tracker.spreads().collect::<Vec<_>>(),
+            vec![5.0, 3.0, 1.0, 1.0]
+        );
+        assert_eq!(tracker.mean_spread(), Some(2.5));
+
+        // a side emptying isn't a spread
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 5,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(101, 0.0), tl(99, 0.0)],
+        });
+        assert_eq!(book.spread_tracker().unwrap().len(), 4);
+
+        let untracked: OrderBook<8, 2> = OrderBook::new(2u8.try_into().unwrap());
+        assert!(untracked.spread_tracker().is_none());
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use crate::{EqualSequencePolicy, HeapPolicy, OrderBook, TickUpdate, tick::Decimals, tick::Tick};
+
+/// everything that decides how the next update lands: cache arrays, heaps,
+/// tick anchors, best indices and the sequence id the equal sequence policy
+/// compares against
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookState<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T: Tick = u32> {
+    pub tick_decimals: Decimals,
+    pub heap_policy: HeapPolicy,
+    pub equal_sequence_policy: EqualSequencePolicy,
+    pub size_step: Option<f64>,
+    pub sequence_id: u64,
+
+    pub asks_0_tick: T,
+    pub bids_0_tick: T,
+
+    pub best_ask_i: u16,
+    pub best_bid_i: u16,
+
+    pub asks: [f64; CACHE_SLOTS],
+    pub bids: [f64; CACHE_SLOTS],
+
+    pub asks_heap: BTreeMap<T, (f64, u64)>,
+    pub bids_heap: BTreeMap<T, (f64, u64)>,
+}
+
+impl<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T: Tick>
+    OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>
+{
+    pub fn state(&self) -> BookState<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T> {
+        BookState {
+            tick_decimals: self.tick_decimals,
+            heap_policy: self.heap_policy,
+            equal_sequence_policy: self.equal_sequence_policy,
+            size_step: self.size_step,
+            sequence_id: self.sequence_id,
+            asks_0_tick: self.asks_0_tick,
+            bids_0_tick: self.bids_0_tick,
+            best_ask_i: self.best_ask_i,
+            best_bid_i: self.best_bid_i,
+            asks: *self.asks,
+            bids: *self.bids,
+            asks_heap: self.asks_heap.clone(),
+            bids_heap: self.bids_heap.clone(),
+        }
+    }
+
+    /// NOTE: the state isn't validated, a hand built state that breaks the
+    /// book invariants breaks the book
+    pub fn from_state(state: BookState<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>) -> Self {
+        let mut book = Self::new(state.tick_decimals)
+            .with_heap_policy(state.heap_policy)
+            .with_equal_sequence_policy(state.equal_sequence_policy);
+        book.size_step = state.size_step;
+        book.sequence_id = state.sequence_id;
+        book.asks_0_tick = state.asks_0_tick;
+        book.bids_0_tick = state.bids_0_tick;
+        book.best_ask_i = state.best_ask_i;
+        book.best_bid_i = state.best_bid_i;
+        *book.asks = state.asks;
+        *book.bids = state.bids;
+        book.asks_heap = state.asks_heap;
+        book.bids_heap = state.bids_heap;
+        book
+    }
+}
+
+/// applies `update` to a copy of `state`, leaving `state` untouched
+pub fn simulate_update<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T: Tick>(
+    state: &BookState<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>,
+    update: &TickUpdate<T>,
+) -> BookState<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T> {
+    let mut book = OrderBook::from_state(state.clone());
+    let _ = book.process_tick_update(update);
+    book.state()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simulate_update;
+    use crate::{EqualSequencePolicy, OrderBook, TickLevel, TickUpdate};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn simulate_update_is_deterministic() {
+        let mut book: OrderBook<5, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 20.0), tl(103, 30.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(97, 30.0)],
+        });
+
+        let update = TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, 0.0), tl(105, 50.0), tl(114, 100.0)],
+            bids: vec![tl(100, 1.0), tl(99, 0.0), tl(86, 100.0)],
+        };
+
+        let state = book.state();
+        let a = simulate_update(&state.clone(), &update);
+        let b = simulate_update(&state.clone(), &update);
+
+        println!("{a:#?}");
+
+        assert_eq!(a, b);
+        // the input state is left alone
+        assert_eq!(state, book.state());
+
+        let _ = book.process_tick_update(&update);
+        assert_eq!(a, book.state());
+    }
+
+    #[test]
+    fn simulate_matches_process_on_equal_sequence() {
+        for policy in [
+            EqualSequencePolicy::Apply,
+            EqualSequencePolicy::Ignore,
+            EqualSequencePolicy::Replace,
+        ] {
+            let mut book: OrderBook<5, 1> =
+                OrderBook::new(2u8.try_into().unwrap()).with_equal_sequence_policy(policy);
+            let _ = book.process_tick_update(&TickUpdate {
+                sequence_id: 7,
+                timestamp: None,
+                asks: vec![tl(101, 5.0), tl(102, 20.0)],
+                bids: vec![tl(99, 10.0)],
+            });
+
+            let resend = TickUpdate {
+                sequence_id: 7,
+                timestamp: None,
+                asks: vec![tl(101, 0.0)],
+                bids: vec![tl(98, 3.0)],
+            };
+            let simulated = simulate_update(&book.state(), &resend);
+            assert_eq!(simulated.sequence_id, 7);
+
+            let _ = book.process_tick_update(&resend);
+            println!("{policy:?} {book:#?}");
+            assert_eq!(simulated, book.state(), "{policy:?}");
+        }
+    }
+}
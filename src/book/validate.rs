@@ -0,0 +1,327 @@
+use crate::{
+    OrderBook, Side, cache_storage::CacheStorage, heap_alloc::HeapAlloc, populated, tick::Tick,
+};
+
+/// first broken invariant `validate` found
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvariantViolation<T = u32> {
+    /// a cache slot holds a negative, non finite or nonzero size below
+    /// `EPSILON`. those are stored as 0.0
+    InvalidSize { side: Side, i: usize, size: f64 },
+    /// the best index isn't the first populated cache slot, `expected` is
+    /// `None` when the cache is empty
+    BestIndex {
+        side: Side,
+        best_i: u16,
+        expected: Option<u16>,
+    },
+    /// the cache is empty while the heap still holds levels
+    HeapWithoutCache { side: Side },
+    /// a heap level sits inside or in front of the cache window
+    HeapOverlapsCache { side: Side, tick: T },
+    /// best bid at or above best ask
+    Crossed { best_bid: T, best_ask: T },
+}
+
+impl<T: Tick> std::fmt::Display for InvariantViolation<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvariantViolation::InvalidSize { side, i, size } => {
+                write!(f, "{side:?} cache slot {i} holds invalid size {size}")
+            }
+            InvariantViolation::BestIndex {
+                side,
+                best_i,
+                expected,
+            } => write!(
+                f,
+                "{side:?} best index {best_i} isn't the first populated slot {expected:?}"
+            ),
+            InvariantViolation::HeapWithoutCache { side } => {
+                write!(f, "{side:?} cache is empty but the heap isn't")
+            }
+            InvariantViolation::HeapOverlapsCache { side, tick } => {
+                write!(f, "{side:?} heap tick {tick} overlaps the cache window")
+            }
+            InvariantViolation::Crossed { best_bid, best_ask } => {
+                write!(
+                    f,
+                    "crossed book, best bid {best_bid} >= best ask {best_ask}"
+                )
+            }
+        }
+    }
+}
+
+/// Error when the best bid isn't strictly below the best ask
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookOrderingError<T = u32> {
+    /// best bid and best ask share a tick
+    Locked { tick: T },
+    /// best bid above best ask, the sides flipped
+    Crossed { best_bid: T, best_ask: T },
+}
+
+impl<T: Tick> std::fmt::Display for BookOrderingError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BookOrderingError::Locked { tick } => {
+                write!(f, "locked book, best bid and best ask at {tick}")
+            }
+            BookOrderingError::Crossed { best_bid, best_ask } => {
+                write!(f, "crossed book, best bid {best_bid} > best ask {best_ask}")
+            }
+        }
+    }
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    /// checks best bid < best ask, O(1) and cheap enough to call after every
+    /// update. a book with an empty side is ordered. a single book can't
+    /// tell which side is stale, both ticks are returned
+    pub fn assert_ordered(&self) -> Result<(), BookOrderingError<T>> {
+        let (Some(best_ask), Some(best_bid)) = (self.best_ask_tick(), self.best_bid_tick()) else {
+            return Ok(());
+        };
+
+        match best_bid.cmp(&best_ask) {
+            std::cmp::Ordering::Less => Ok(()),
+            std::cmp::Ordering::Equal => Err(BookOrderingError::Locked { tick: best_bid }),
+            std::cmp::Ordering::Greater => Err(BookOrderingError::Crossed { best_bid, best_ask }),
+        }
+    }
+
+    /// checks the book's internal invariants, for catching corruption in
+    /// long running processes and tests. walks the whole cache, keep it off
+    /// the hot path
+    pub fn validate(&self) -> Result<(), InvariantViolation<T>> {
+        self.validate_side(Side::Ask)?;
+        self.validate_side(Side::Bid)?;
+
+        if let (Some(best_ask), Some(best_bid)) = (self.best_ask_tick(), self.best_bid_tick())
+            && best_bid >= best_ask
+        {
+            return Err(InvariantViolation::Crossed { best_bid, best_ask });
+        }
+
+        Ok(())
+    }
+
+    fn validate_side(&self, side: Side) -> Result<(), InvariantViolation<T>> {
+        let (cache, best_i, heap_len) = match side {
+            Side::Ask => (&self.asks, self.best_ask_i, self.asks_heap.len()),
+            Side::Bid => (&self.bids, self.best_bid_i, self.bids_heap.len()),
+        };
+
+        if let Some((i, size)) = cache
+            .iter()
+            .enumerate()
+            .find(|(_, size)| **size != 0.0 && !(populated(**size) && size.is_finite()))
+        {
+            return Err(InvariantViolation::InvalidSize {
+                side,
+                i,
+                size: *size,
+            });
+        }
+
+        let expected = cache.iter().position(|sz| populated(*sz)).map(|i| i as u16);
+        match expected {
+            Some(expected) if expected != best_i => {
+                return Err(InvariantViolation::BestIndex {
+                    side,
+                    best_i,
+                    expected: Some(expected),
+                });
+            }
+            None if heap_len > 0 => return Err(InvariantViolation::HeapWithoutCache { side }),
+            _ => {}
+        }
+
+        // the heap only holds levels beyond the far end of the window
+        let overlap = match side {
+            Side::Ask => {
+                let last = self
+                    .asks_0_tick
+                    .saturating_add(T::from_index(CACHE_SLOTS - 1));
+                self.asks_heap.keys().next().filter(|tick| **tick <= last)
+            }
+            Side::Bid => {
+                let last = self
+                    .bids_0_tick
+                    .saturating_sub(T::from_index(CACHE_SLOTS - 1));
+                self.bids_heap
+                    .keys()
+                    .next_back()
+                    .filter(|tick| **tick >= last)
+            }
+        };
+        if let Some(tick) = overlap {
+            return Err(InvariantViolation::HeapOverlapsCache { side, tick: *tick });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "internals")]
+    use super::InvariantViolation;
+    use crate::{BookOrderingError, OrderBook, TickLevel, TickUpdate};
+    #[cfg(feature = "internals")]
+    use crate::{EPSILON, Side, UpdateOutcome};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    fn book() -> OrderBook<4, 1> {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
+        });
+        book
+    }
+
+    #[test]
+    fn valid_book() {
+        let mut book = book();
+        assert_eq!(book.validate(), Ok(()));
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![tl(101, 0.0), tl(102, 0.0)],
+            bids: vec![tl(100, 1.0)],
+        });
+        assert_eq!(book.validate(), Ok(()));
+    }
+
+    #[cfg(feature = "internals")]
+    #[test]
+    fn detects_violations() {
+        // asks window is 100..=103, bids 100..=97
+        let valid = book();
+
+        let mut book = valid.clone();
+        book.set_cache_slot(Side::Ask, 3, f64::NAN);
+        assert!(matches!(
+            book.validate(),
+            Err(InvariantViolation::InvalidSize {
+                side: Side::Ask,
+                i: 3,
+                ..
+            })
+        ));
+
+        // dust and negatives are stored as 0.0 and never reach the cache
+        for size in [-1.0, EPSILON / 2.0] {
+            let mut book = valid.clone();
+            book.set_cache_slot(Side::Bid, 2, size);
+            assert!(matches!(
+                book.validate(),
+                Err(InvariantViolation::InvalidSize {
+                    side: Side::Bid,
+                    i: 2,
+                    ..
+                })
+            ));
+        }
+
+        let mut book = valid.clone();
+        book.set_best_index(Side::Bid, 2);
+        assert_eq!(
+            book.validate(),
+            Err(InvariantViolation::BestIndex {
+                side: Side::Bid,
+                best_i: 2,
+                expected: Some(1),
+            })
+        );
+
+        let mut book = valid.clone();
+        book.set_cache_slot(Side::Ask, 1, 0.0);
+        book.set_cache_slot(Side::Ask, 2, 0.0);
+        assert_eq!(
+            book.validate(),
+            Err(InvariantViolation::HeapWithoutCache { side: Side::Ask })
+        );
+
+        let mut book = valid.clone();
+        book.insert_heap_level(Side::Bid, 98, 1.0);
+        assert_eq!(
+            book.validate(),
+            Err(InvariantViolation::HeapOverlapsCache {
+                side: Side::Bid,
+                tick: 98,
+            })
+        );
+
+        let mut book = valid.clone();
+        assert_eq!(
+            book.process_tick_update(&TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![tl(101, 1.0)],
+            }),
+            UpdateOutcome::Changed
+        );
+        let violation = book.validate().unwrap_err();
+        println!("{violation}");
+        assert_eq!(
+            violation,
+            InvariantViolation::Crossed {
+                best_bid: 101,
+                best_ask: 101,
+            }
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let mut book = book();
+        assert_eq!(book.assert_ordered(), Ok(()));
+        assert_eq!(OrderBook::<4, 1>::default().assert_ordered(), Ok(()));
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 1,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(101, 1.0)],
+        });
+        assert_eq!(
+            book.assert_ordered(),
+            Err(BookOrderingError::Locked { tick: 101 })
+        );
+
+        // flipped, the bid moved through the whole ask side
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 2,
+            timestamp: None,
+            asks: vec![],
+            bids: vec![tl(105, 1.0)],
+        });
+        println!("{book:#?}");
+        let err = book.assert_ordered().unwrap_err();
+        println!("{err}");
+        assert_eq!(
+            err,
+            BookOrderingError::Crossed {
+                best_bid: 105,
+                best_ask: 101,
+            }
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{
+    OrderBook, TickLevel, cache_storage::CacheStorage, heap_alloc::HeapAlloc,
+    old_book::BTreeOrderBook, populated, tick::Tick,
+};
+
+/// read only view of a book's levels, for comparing book implementations
+pub trait BookView<T: Tick = u32> {
+    /// live asks, lowest to highest
+    fn ask_levels(&self) -> impl Iterator<Item = TickLevel<T>> + '_;
+    /// live bids, highest to lowest
+    fn bid_levels(&self) -> impl Iterator<Item = TickLevel<T>> + '_;
+
+    fn best_ask_level(&self) -> Option<TickLevel<T>> {
+        self.ask_levels().next()
+    }
+    fn best_bid_level(&self) -> Option<TickLevel<T>> {
+        self.bid_levels().next()
+    }
+}
+
+impl<
+    const CACHE_SLOTS: usize,
+    const CACHE_EMPTY_SLOTS: usize,
+    T: Tick,
+    A: HeapAlloc,
+    C: CacheStorage<CACHE_SLOTS>,
+> BookView<T> for OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T, A, C>
+{
+    fn ask_levels(&self) -> impl Iterator<Item = TickLevel<T>> + '_ {
+        self.ask_ticks()
+    }
+    fn bid_levels(&self) -> impl Iterator<Item = TickLevel<T>> + '_ {
+        self.bid_ticks()
+    }
+}
+
+impl BookView for BTreeOrderBook {
+    fn ask_levels(&self) -> impl Iterator<Item = TickLevel> + '_ {
+        self.asks().filter(|level| populated(level.size))
+    }
+    fn bid_levels(&self) -> impl Iterator<Item = TickLevel> + '_ {
+        self.bids().filter(|level| populated(level.size))
+    }
+
+    // the cached bba, not recomputed from the levels
+    fn best_ask_level(&self) -> Option<TickLevel> {
+        self.best_ask()
+    }
+    fn best_bid_level(&self) -> Option<TickLevel> {
+        self.best_bid()
+    }
+}
+
+/// `a` and `b` hold the same best levels and the same level lists, ticks
+/// equal and sizes within `eps`
+pub fn books_equivalent<T: Tick>(a: &impl BookView<T>, b: &impl BookView<T>, eps: f64) -> bool {
+    let same = |a: Option<TickLevel<T>>, b: Option<TickLevel<T>>| match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => a.tick == b.tick && (a.size - b.size).abs() <= eps,
+        _ => false,
+    };
+    let same_levels = |a: &mut dyn Iterator<Item = TickLevel<T>>,
+                       b: &mut dyn Iterator<Item = TickLevel<T>>| {
+        loop {
+            match (a.next(), b.next()) {
+                (None, None) => return true,
+                (a, b) if same(a, b) => {}
+                _ => return false,
+            }
+        }
+    };
+
+    same(a.best_ask_level(), b.best_ask_level())
+        && same(a.best_bid_level(), b.best_bid_level())
+        && same_levels(&mut a.ask_levels(), &mut b.ask_levels())
+        && same_levels(&mut a.bid_levels(), &mut b.bid_levels())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OrderBook, TickLevel, TickUpdate, books_equivalent, old_book::BTreeOrderBook};
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    fn update() -> TickUpdate {
+        TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(98, 20.0), tl(90, 40.0)],
+        }
+    }
+
+    #[test]
+    fn equivalent_books() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let mut btree = BTreeOrderBook::new();
+        let _ = book.process_tick_update(&update());
+        btree.process_tick_update(&update());
+
+        println!("{book:#?}");
+        assert!(books_equivalent(&book, &btree, 1e-9));
+        assert!(books_equivalent(&btree, &book, 1e-9));
+
+        // float noise within tolerance
+        let mut noisy = update();
+        noisy.asks[2].size += 1e-12;
+        btree.process_tick_update(&noisy);
+        assert!(books_equivalent(&book, &btree, 1e-9));
+        assert!(!books_equivalent(&book, &btree, 0.0));
+    }
+
+    #[test]
+    fn one_level_off() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&update());
+
+        let mut deeper = update();
+        deeper.bids.push(tl(80, 1.0));
+        let mut btree = BTreeOrderBook::new();
+        btree.process_tick_update(&deeper);
+        assert!(!books_equivalent(&book, &btree, 1e-9));
+
+        let mut moved = update();
+        moved.asks[1].tick = 103;
+        let mut other: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = other.process_tick_update(&moved);
+        assert!(!books_equivalent(&book, &other, 1e-9));
+    }
+}
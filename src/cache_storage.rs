@@ -0,0 +1,66 @@
+//! where a book keeps its two cache arrays.
+//!
+//! `Inline` keeps them in the book itself, the default and the fastest for
+//! small caches. `Box<[f64; N]>` moves them to the heap: with thousands of
+//! slots an inline book is tens of KB, too big for the stack and copied
+//! inline on every clone. see `BoxedOrderBook` and the `clone` bench
+
+use std::ops::{Deref, DerefMut};
+
+pub trait CacheStorage<const N: usize>:
+    Deref<Target = [f64; N]> + DerefMut + Clone + std::fmt::Debug + Send + Sync + sealed::Sealed
+{
+    /// every slot 0.0
+    fn zeroed() -> Self;
+}
+
+/// cache array stored inline in the book
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct Inline<const N: usize>([f64; N]);
+
+impl<const N: usize> Deref for Inline<N> {
+    type Target = [f64; N];
+
+    #[inline(always)]
+    fn deref(&self) -> &[f64; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for Inline<N> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut [f64; N] {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> std::fmt::Debug for Inline<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<const N: usize> CacheStorage<N> for Inline<N> {
+    #[inline]
+    fn zeroed() -> Self {
+        Self([0.0; N])
+    }
+}
+
+impl<const N: usize> CacheStorage<N> for Box<[f64; N]> {
+    #[inline]
+    fn zeroed() -> Self {
+        // through a Vec, `Box::new([0.0; N])` can build the array on the stack
+        vec![0.0; N]
+            .into_boxed_slice()
+            .try_into()
+            .expect("boxed slice of N slots")
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl<const N: usize> Sealed for super::Inline<N> {}
+    impl<const N: usize> Sealed for Box<[f64; N]> {}
+}
@@ -0,0 +1,66 @@
+use futures_util::{Stream, StreamExt};
+
+use crate::{OrderBook, TickUpdate, tick::Tick};
+
+/// applies every update from `stream` to `book` as it arrives, calling
+/// `on_update` after each one. returns when the stream ends
+pub async fn run<const CACHE_SLOTS: usize, const CACHE_EMPTY_SLOTS: usize, T, S>(
+    book: &mut OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>,
+    stream: S,
+    mut on_update: impl FnMut(&OrderBook<CACHE_SLOTS, CACHE_EMPTY_SLOTS, T>),
+) where
+    T: Tick,
+    S: Stream<Item = TickUpdate<T>>,
+{
+    let mut stream = std::pin::pin!(stream);
+
+    while let Some(update) = stream.next().await {
+        let _ = book.process_tick_update(&update);
+        on_update(book);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TickLevel;
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[test]
+    fn run_stream() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+
+        let updates = vec![
+            TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![tl(101, 5.0)],
+                bids: vec![tl(99, 10.0)],
+            },
+            TickUpdate {
+                sequence_id: 2,
+                timestamp: None,
+                asks: vec![tl(101, 0.0), tl(102, 15.0)],
+                bids: vec![],
+            },
+            TickUpdate {
+                sequence_id: 3,
+                timestamp: None,
+                asks: vec![],
+                bids: vec![tl(98, 20.0)],
+            },
+        ];
+
+        let mut seen = Vec::new();
+        futures::executor::block_on(run(&mut book, futures::stream::iter(updates), |book| {
+            seen.push((book.sequence_id(), book.best_ask().unwrap().price))
+        }));
+
+        assert_eq!(seen, vec![(1, 1.01), (2, 1.02), (3, 1.02)]);
+        assert_eq!(book.sequence_id(), 3);
+        assert_eq!(book.best_bid().unwrap().price, 0.99);
+    }
+}
@@ -0,0 +1,168 @@
+//! allocator the book's heaps allocate through.
+//!
+//! custom allocators need nightly and the `allocator_api` feature, stable
+//! builds only have `Global`
+//!
+//! the heaps are `BTreeMap`s, which can't reserve capacity. to keep a spill
+//! burst off the global allocator, build the book with `new_in` over an
+//! `Arena` reserved up front
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "allocator_api")]
+pub use std::alloc::{Allocator, Global};
+#[cfg(feature = "allocator_api")]
+use std::{
+    alloc::{AllocError, Layout},
+    ptr::NonNull,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+#[cfg(feature = "allocator_api")]
+pub trait HeapAlloc: Allocator + Clone {}
+
+#[cfg(feature = "allocator_api")]
+impl<A: Allocator + Clone> HeapAlloc for A {}
+
+/// bump allocator over one block reserved up front, for heaps that should
+/// take a spill burst without touching the global allocator. clones share
+/// the block, one arena serves both heaps of a book.
+///
+/// past its capacity it allocates from `Global`, counted by `fallbacks`.
+///
+/// NOTE: memory the heaps free isn't reused, it's released when the last
+/// clone drops. size it for the levels a book ever spills, not the levels
+/// it holds at once
+#[cfg(feature = "allocator_api")]
+#[derive(Debug, Clone)]
+pub struct Arena(Arc<Block>);
+
+#[cfg(feature = "allocator_api")]
+#[derive(Debug)]
+struct Block {
+    base: NonNull<u8>,
+    layout: Layout,
+    used: AtomicUsize,
+    fallbacks: AtomicUsize,
+}
+
+// SAFETY the block is only handed out in disjoint ranges, bumped atomically
+#[cfg(feature = "allocator_api")]
+unsafe impl Send for Block {}
+#[cfg(feature = "allocator_api")]
+unsafe impl Sync for Block {}
+
+#[cfg(feature = "allocator_api")]
+impl Drop for Block {
+    fn drop(&mut self) {
+        // SAFETY allocated in `with_capacity` with this layout
+        unsafe { Global.deallocate(self.base, self.layout) }
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl Arena {
+    /// reserves `bytes` from `Global` now
+    pub fn with_capacity(bytes: usize) -> Self {
+        let layout = Layout::from_size_align(bytes.max(1), 64).expect("arena too large");
+        let base = Global
+            .allocate(layout)
+            .unwrap_or_else(|_| std::alloc::handle_alloc_error(layout))
+            .cast();
+        Arena(Arc::new(Block {
+            base,
+            layout,
+            used: AtomicUsize::new(0),
+            fallbacks: AtomicUsize::new(0),
+        }))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.0.layout.size()
+    }
+
+    /// bytes handed out from the block, alignment padding included
+    pub fn used(&self) -> usize {
+        self.0.used.load(Ordering::Relaxed)
+    }
+
+    /// allocations that didn't fit and went to `Global`
+    pub fn fallbacks(&self) -> usize {
+        self.0.fallbacks.load(Ordering::Relaxed)
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let offset = (ptr.as_ptr() as usize).wrapping_sub(self.0.base.as_ptr() as usize);
+        offset < self.capacity()
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+unsafe impl Allocator for Arena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let block = &self.0;
+        let base = block.base.as_ptr() as usize;
+        let bumped = block
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                let start = (base + used).next_multiple_of(layout.align()) - base;
+                let end = start.checked_add(layout.size())?;
+                (end <= block.layout.size()).then_some(end)
+            });
+
+        match bumped {
+            Ok(used) => {
+                let start = (base + used).next_multiple_of(layout.align()) - base;
+                // SAFETY start + size fit the block, checked in the bump
+                let ptr = unsafe { block.base.add(start) };
+                Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+            }
+            Err(_) => {
+                block.fallbacks.fetch_add(1, Ordering::Relaxed);
+                Global.allocate(layout)
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        // block memory is released with the block
+        if !self.owns(ptr) {
+            // SAFETY not from the block, so it came from `Global`
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+}
+
+/// the global allocator
+#[cfg(not(feature = "allocator_api"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Global;
+
+#[cfg(not(feature = "allocator_api"))]
+pub trait HeapAlloc: Clone + sealed::Sealed {}
+
+#[cfg(not(feature = "allocator_api"))]
+impl HeapAlloc for Global {}
+
+#[cfg(not(feature = "allocator_api"))]
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for super::Global {}
+}
+
+/// empty heap allocating through `alloc`
+#[cfg(feature = "allocator_api")]
+#[inline]
+pub(crate) fn new_heap<T, A: HeapAlloc>(alloc: &A) -> BTreeMap<T, (f64, u64), A> {
+    BTreeMap::new_in(alloc.clone())
+}
+
+/// empty heap allocating through `alloc`
+#[cfg(not(feature = "allocator_api"))]
+#[inline]
+pub(crate) fn new_heap<T, A: HeapAlloc>(_alloc: &A) -> BTreeMap<T, (f64, u64)> {
+    BTreeMap::new()
+}
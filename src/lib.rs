@@ -1,48 +1,299 @@
+#![cfg_attr(feature = "allocator_api", feature(allocator_api, btreemap_alloc))]
+
 use tabled::Tabled;
+use tick::{Decimals, Tick, TickConversionError};
 
 mod book;
+pub mod cache_storage;
+#[cfg(feature = "async")]
+pub mod feed;
+pub mod heap_alloc;
 pub mod lookup_tables;
 pub mod old_book;
+#[cfg(feature = "recorder")]
+pub mod replay;
 pub mod tick;
 
 pub use book::*;
 
-#[derive(Debug, Clone, Copy, Default, Tabled)]
-pub struct TickLevel {
-    pub tick: u32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Tabled)]
+pub struct TickLevel<T: Tick = u32> {
+    pub tick: T,
     pub size: f64,
 }
 
-#[derive(Debug, Clone, Copy, Default, Tabled)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Tabled)]
 pub struct FloatLevel {
     pub price: f64,
     pub size: f64,
 }
 
-#[derive(Debug, Clone)]
-pub struct TickUpdate {
+/// a price level of either representation, for helpers that work over both
+pub trait Level: Copy {
+    /// the tick for `TickLevel`, the price for `FloatLevel`
+    type Price: Copy + PartialOrd + std::fmt::Display;
+
+    fn price(&self) -> Self::Price;
+    fn size(&self) -> f64;
+}
+
+impl<T: Tick> Level for TickLevel<T> {
+    type Price = T;
+
+    #[inline]
+    fn price(&self) -> T {
+        self.tick
+    }
+    #[inline]
+    fn size(&self) -> f64 {
+        self.size
+    }
+}
+
+impl Level for FloatLevel {
+    type Price = f64;
+
+    #[inline]
+    fn price(&self) -> f64 {
+        self.price
+    }
+    #[inline]
+    fn size(&self) -> f64 {
+        self.size
+    }
+}
+
+impl FloatLevel {
+    /// price rounded to the nearest tick of any width, size kept as is
+    pub fn to_tick<T: Tick>(
+        &self,
+        decimals: &Decimals,
+    ) -> Result<TickLevel<T>, TickConversionError> {
+        Ok(TickLevel {
+            tick: decimals.f64_to_tick(self.price)?,
+            size: self.size,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TickUpdate<T: Tick = u32> {
     pub sequence_id: u64,
+    /// optional exchange/wall-clock time in nanos
+    pub timestamp: Option<u64>,
     /// invariant: ask_levels must be sorted lowest to highest price
-    pub asks: Vec<TickLevel>, // Vec<T, I> newtype to track invariants like pointer from zerocopy cool idea to mark sorted
+    pub asks: Vec<TickLevel<T>>, // Vec<T, I> newtype to track invariants like pointer from zerocopy cool idea to mark sorted
     /// invariant: bid_levels must be sorted highest to lowest price
-    pub bids: Vec<TickLevel>,
+    pub bids: Vec<TickLevel<T>>,
 }
 
-impl TickUpdate {
+impl<T: Tick> TickUpdate<T> {
     #[inline]
-    pub fn best_bid(&self) -> Option<TickLevel> {
+    pub fn best_bid(&self) -> Option<TickLevel<T>> {
         self.bids.first().copied()
     }
     #[inline]
-    pub fn bids(&self) -> impl ExactSizeIterator<Item = TickLevel> {
+    pub fn bids(&self) -> impl ExactSizeIterator<Item = TickLevel<T>> {
         self.bids.iter().copied()
     }
     #[inline]
-    pub fn best_ask(&self) -> Option<TickLevel> {
+    pub fn best_ask(&self) -> Option<TickLevel<T>> {
         self.asks.first().copied()
     }
     #[inline]
-    pub fn asks(&self) -> impl ExactSizeIterator<Item = TickLevel> {
+    pub fn asks(&self) -> impl ExactSizeIterator<Item = TickLevel<T>> {
         self.asks.iter().copied()
     }
 }
+
+/// union of two updates of the same book, e.g. from redundant feeds. a
+/// tick in both keeps the level of the higher `sequence_id`, or the larger
+/// size when the ids are equal. the result takes the higher `sequence_id`
+/// and the later timestamp
+///
+/// invariant: both updates sorted as `TickUpdate` requires, the result is too
+pub fn merge_updates<T: Tick>(a: &TickUpdate<T>, b: &TickUpdate<T>) -> TickUpdate<T> {
+    let pick = |x: TickLevel<T>, y: TickLevel<T>| match a.sequence_id.cmp(&b.sequence_id) {
+        std::cmp::Ordering::Greater => x,
+        std::cmp::Ordering::Less => y,
+        std::cmp::Ordering::Equal if y.size > x.size => y,
+        std::cmp::Ordering::Equal => x,
+    };
+
+    TickUpdate {
+        sequence_id: a.sequence_id.max(b.sequence_id),
+        timestamp: a.timestamp.max(b.timestamp),
+        asks: merge_side(&a.asks, &b.asks, |x, y| x.cmp(&y), pick),
+        bids: merge_side(&a.bids, &b.bids, |x, y| y.cmp(&x), pick),
+    }
+}
+
+/// merge join of two sides sorted best first by `order`
+fn merge_side<T: Tick>(
+    a: &[TickLevel<T>],
+    b: &[TickLevel<T>],
+    order: impl Fn(T, T) -> std::cmp::Ordering,
+    pick: impl Fn(TickLevel<T>, TickLevel<T>) -> TickLevel<T>,
+) -> Vec<TickLevel<T>> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let (mut a, mut b) = (a.iter().copied().peekable(), b.iter().copied().peekable());
+
+    loop {
+        let level = match (a.peek(), b.peek()) {
+            (None, None) => return merged,
+            (Some(_), None) => a.next(),
+            (None, Some(_)) => b.next(),
+            (Some(x), Some(y)) => match order(x.tick, y.tick) {
+                std::cmp::Ordering::Less => a.next(),
+                std::cmp::Ordering::Greater => b.next(),
+                std::cmp::Ordering::Equal => a.next().zip(b.next()).map(|(x, y)| pick(x, y)),
+            },
+        };
+        merged.extend(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        FloatLevel, Level, OrderBook, TickLevel, TickUpdate, merge_updates,
+        tick::TickConversionError,
+    };
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    /// total size of asks priced at or below `limit`
+    fn size_up_to<L: Level>(levels: impl IntoIterator<Item = L>, limit: L::Price) -> f64 {
+        levels
+            .into_iter()
+            .take_while(|level| level.price() <= limit)
+            .map(|level| level.size())
+            .sum()
+    }
+
+    #[test]
+    fn float_level_to_tick() {
+        let decimals = 2u8.try_into().unwrap();
+        let level = FloatLevel {
+            price: 101.25,
+            size: 3.5,
+        };
+
+        let tick: TickLevel = level.to_tick(&decimals).unwrap();
+        assert_eq!(
+            tick,
+            TickLevel {
+                tick: 10125,
+                size: 3.5
+            }
+        );
+        assert_eq!(
+            FloatLevel {
+                price: decimals.tick_to_f64(tick.tick),
+                size: tick.size,
+            },
+            level
+        );
+
+        let nan = FloatLevel {
+            price: f64::NAN,
+            size: 1.0,
+        };
+        assert_eq!(
+            nan.to_tick::<u32>(&decimals),
+            Err(TickConversionError::NotFinite)
+        );
+        let huge = FloatLevel {
+            price: 1e12,
+            size: 1.0,
+        };
+        assert_eq!(
+            huge.to_tick::<u32>(&decimals),
+            Err(TickConversionError::OutOfRange)
+        );
+        assert_eq!(
+            huge.to_tick::<u64>(&decimals),
+            Ok(TickLevel {
+                tick: 100_000_000_000_000,
+                size: 1.0
+            })
+        );
+    }
+
+    #[test]
+    fn generic_levels() {
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: None,
+            asks: vec![
+                TickLevel {
+                    tick: 101,
+                    size: 5.0,
+                },
+                TickLevel {
+                    tick: 102,
+                    size: 15.0,
+                },
+                TickLevel {
+                    tick: 110,
+                    size: 35.0,
+                },
+            ],
+            bids: vec![],
+        });
+
+        assert_eq!(size_up_to(book.ask_ticks(), 102), 20.0);
+        assert_eq!(size_up_to(book.asks(), 1.02), 20.0);
+        assert_eq!(size_up_to(book.asks(), 2.0), 55.0);
+    }
+
+    #[test]
+    fn merge_overlapping_updates() {
+        let a = TickUpdate {
+            sequence_id: 5,
+            timestamp: Some(100),
+            asks: vec![tl(101, 5.0), tl(103, 1.0), tl(110, 35.0)],
+            bids: vec![tl(99, 10.0), tl(97, 2.0)],
+        };
+        let b = TickUpdate {
+            sequence_id: 6,
+            timestamp: None,
+            asks: vec![tl(101, 4.0), tl(102, 15.0)],
+            bids: vec![tl(100, 1.0), tl(97, 0.0), tl(90, 40.0)],
+        };
+
+        let merged = merge_updates(&a, &b);
+        println!("{merged:#?}");
+        assert_eq!(merged.sequence_id, 6);
+        assert_eq!(merged.timestamp, Some(100));
+        // b is newer, its sizes win, removals included
+        assert_eq!(
+            merged.asks,
+            vec![tl(101, 4.0), tl(102, 15.0), tl(103, 1.0), tl(110, 35.0)]
+        );
+        assert_eq!(
+            merged.bids,
+            vec![tl(100, 1.0), tl(99, 10.0), tl(97, 0.0), tl(90, 40.0)]
+        );
+        assert_eq!(merge_updates(&b, &a).asks, merged.asks);
+
+        // same sequence id, larger size wins
+        let b = TickUpdate {
+            sequence_id: 5,
+            ..b
+        };
+        let merged = merge_updates(&a, &b);
+        assert_eq!(merged.asks[0], tl(101, 5.0));
+        assert_eq!(merged.bids[2], tl(97, 2.0));
+        assert_eq!(merge_updates(&b, &a).bids, merged.bids);
+    }
+}
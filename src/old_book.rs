@@ -37,6 +37,24 @@ impl BTreeOrderBook {
     pub fn sequence_id(&self) -> u64 {
         self.last_sequence
     }
+
+    pub fn best_ask(&self) -> Option<TickLevel> {
+        self.best_ask
+    }
+
+    pub fn best_bid(&self) -> Option<TickLevel> {
+        self.best_bid
+    }
+
+    /// lowest to highest
+    pub fn asks(&self) -> impl DoubleEndedIterator<Item = TickLevel> + '_ {
+        self.asks.values().copied()
+    }
+
+    /// highest to lowest
+    pub fn bids(&self) -> impl DoubleEndedIterator<Item = TickLevel> + '_ {
+        self.bids.values().rev().copied()
+    }
 }
 
 impl BTreeOrderBook {
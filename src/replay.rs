@@ -0,0 +1,305 @@
+//! recording and replaying the raw update stream, for reproducing a book
+//! from production.
+//!
+//! each update is written length prefixed, all integers little endian:
+//!
+//! ```text
+//! u32 payload length
+//! u64 sequence_id
+//! u8  has timestamp, then u64 timestamp when 1
+//! u32 ask count, then per ask u64 tick, f64 size
+//! u32 bid count, then per bid u64 tick, f64 size
+//! ```
+
+use std::io::{self, Read, Write};
+
+use crate::{TickLevel, TickUpdate, tick::Tick};
+
+/// writes `update` as one length prefixed record
+pub fn write_update<T: Tick>(w: &mut impl Write, update: &TickUpdate<T>) -> io::Result<()> {
+    let levels = update.asks.len() + update.bids.len();
+    let mut buf = Vec::with_capacity(4 + 8 + 9 + 8 + levels * 16);
+    buf.extend_from_slice(&[0; 4]);
+
+    buf.extend_from_slice(&update.sequence_id.to_le_bytes());
+    match update.timestamp {
+        Some(timestamp) => {
+            buf.push(1);
+            buf.extend_from_slice(&timestamp.to_le_bytes());
+        }
+        None => buf.push(0),
+    }
+    for levels in [&update.asks, &update.bids] {
+        buf.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+        for level in levels {
+            buf.extend_from_slice(&level.tick.to_u64().to_le_bytes());
+            buf.extend_from_slice(&level.size.to_le_bytes());
+        }
+    }
+
+    let len = (buf.len() - 4) as u32;
+    buf[..4].copy_from_slice(&len.to_le_bytes());
+    w.write_all(&buf)
+}
+
+/// reads the next record, `None` at a clean end of stream
+pub fn read_update<T: Tick>(r: &mut impl Read) -> io::Result<Option<TickUpdate<T>>> {
+    let mut len = [0; 4];
+    match r.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+
+    let mut payload = vec![0; u32::from_le_bytes(len) as usize];
+    r.read_exact(&mut payload)?;
+    let mut payload = Payload(&payload);
+
+    let sequence_id = payload.u64()?;
+    let timestamp = match payload.u8()? {
+        0 => None,
+        _ => Some(payload.u64()?),
+    };
+    let asks = payload.levels()?;
+    let bids = payload.levels()?;
+
+    Ok(Some(TickUpdate {
+        sequence_id,
+        timestamp,
+        asks,
+        bids,
+    }))
+}
+
+/// every update recorded in `r`, in order
+pub fn replay<T: Tick, R: Read>(mut r: R) -> impl Iterator<Item = io::Result<TickUpdate<T>>> {
+    std::iter::from_fn(move || read_update(&mut r).transpose())
+}
+
+struct Payload<'a>(&'a [u8]);
+
+impl Payload<'_> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let Some((head, rest)) = self.0.split_first_chunk() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "truncated update record",
+            ));
+        };
+        self.0 = rest;
+        Ok(*head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn levels<T: Tick>(&mut self) -> io::Result<Vec<TickLevel<T>>> {
+        let count = u32::from_le_bytes(self.take()?) as usize;
+        (0..count)
+            .map(|_| {
+                let tick = T::try_from_u64(self.u64()?).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "tick out of range")
+                })?;
+                let size = f64::from_le_bytes(self.take()?);
+                Ok(TickLevel { tick, size })
+            })
+            .collect()
+    }
+}
+
+/// the writer a book tees its updates into, see `OrderBook::attach_recorder`.
+/// a cloned book doesn't record
+#[derive(Default)]
+pub(crate) struct Recorder {
+    writer: Option<Box<dyn Write + Send + Sync>>,
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    #[inline]
+    pub(crate) fn is_attached(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    pub(crate) fn attach(&mut self, writer: impl Write + Send + Sync + 'static) {
+        self.writer = Some(Box::new(writer));
+        self.error = None;
+    }
+
+    /// records `update`. the first write error detaches the writer, it's
+    /// reported by `detach`
+    #[inline]
+    pub(crate) fn record<T: Tick>(&mut self, update: &TickUpdate<T>) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+        if let Err(err) = write_update(writer, update) {
+            self.writer = None;
+            self.error = Some(err);
+        }
+    }
+
+    pub(crate) fn detach(&mut self) -> io::Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        self.error.take().map_or(Ok(()), Err)
+    }
+}
+
+impl Clone for Recorder {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .field("attached", &self.writer.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::OrderBook;
+
+    fn tl(tick: u32, size: f64) -> TickLevel {
+        TickLevel { tick, size }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let updates = [
+            TickUpdate {
+                sequence_id: 0,
+                timestamp: Some(1_000),
+                asks: vec![tl(101, 5.0), tl(102, 15.0), tl(110, 35.0)],
+                bids: vec![tl(99, 10.0), tl(98, 20.0)],
+            },
+            TickUpdate {
+                sequence_id: 1,
+                timestamp: None,
+                asks: vec![tl(101, 0.0)],
+                bids: vec![tl(100, 1.5), tl(90, 40.0)],
+            },
+            TickUpdate {
+                sequence_id: 2,
+                timestamp: Some(3_000),
+                asks: vec![],
+                bids: vec![tl(100, 0.0)],
+            },
+        ];
+
+        let buf = SharedBuf::default();
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        book.attach_recorder(buf.clone());
+        for update in &updates {
+            let _ = book.process_tick_update(update);
+        }
+        book.detach_recorder().unwrap();
+
+        // detached, no longer recorded
+        let _ = book.process_tick_update(&TickUpdate::default());
+
+        let recording = buf.0.lock().unwrap().clone();
+        let replayed: Vec<TickUpdate> = replay(recording.as_slice())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(replayed.len(), updates.len());
+
+        let mut original: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        let mut copy: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        for (update, replayed) in updates.iter().zip(&replayed) {
+            assert_eq!(update.asks, replayed.asks);
+            assert_eq!(update.bids, replayed.bids);
+            let _ = original.process_tick_update(update);
+            let _ = copy.process_tick_update(replayed);
+        }
+
+        println!("{copy:#?}");
+
+        assert_eq!(copy.state_hash(), original.state_hash());
+        assert_eq!(copy.last_timestamp(), Some(3_000));
+    }
+
+    #[test]
+    fn truncated_record() {
+        let mut recording = vec![];
+        write_update(
+            &mut recording,
+            &TickUpdate {
+                sequence_id: 7,
+                timestamp: None,
+                asks: vec![tl(101, 5.0)],
+                bids: vec![],
+            },
+        )
+        .unwrap();
+        recording.truncate(recording.len() - 3);
+
+        let err = read_update::<u32>(&mut recording.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(read_update::<u32>(&mut [].as_slice()).unwrap().is_none());
+    }
+
+    #[test]
+    fn direct_writes_replay() {
+        let buf = SharedBuf::default();
+        let mut book: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        book.attach_recorder(buf.clone());
+
+        let _ = book.process_tick_update(&TickUpdate {
+            sequence_id: 0,
+            timestamp: Some(1_000),
+            asks: vec![tl(101, 5.0), tl(102, 15.0)],
+            bids: vec![tl(99, 10.0)],
+        });
+        // the 0.0 ask is skipped by the merge, it must not replay as a removal
+        let _ = book.merge_snapshot(&TickUpdate {
+            sequence_id: 1,
+            timestamp: Some(2_000),
+            asks: vec![tl(101, 0.0), tl(110, 35.0)],
+            bids: vec![tl(98, 20.0)],
+        });
+        let _ = book.update_asks(2, &[tl(101, 7.0), tl(103, 1.0)]);
+        let _ = book.update_bids(3, &[tl(99, 0.0)]);
+        let _ = book.adjust_ask(102, -5.0);
+        let _ = book.adjust_bid(98, -25.0);
+        book.detach_recorder().unwrap();
+
+        let recording = buf.0.lock().unwrap().clone();
+        let mut copy: OrderBook<4, 1> = OrderBook::new(2u8.try_into().unwrap());
+        for update in replay::<u32, _>(recording.as_slice()) {
+            let _ = copy.process_tick_update(&update.unwrap());
+        }
+        println!("{copy:#?}");
+
+        assert_eq!(copy.best_ask_tick(), Some(101));
+        assert_eq!(copy.best_bid(), None);
+        assert_eq!(copy.state_hash(), book.state_hash());
+        assert_eq!(copy.last_timestamp(), book.last_timestamp());
+    }
+}
@@ -1,8 +1,112 @@
-use std::{convert::TryFrom, fmt::Display};
+use std::{
+    convert::TryFrom,
+    fmt::{Debug, Display},
+    hash::Hash,
+    ops::{Add, AddAssign, Sub, SubAssign},
+};
 
-use crate::lookup_tables::DECIMAL_SHRINK_MULTIPLIERS_F64;
+#[cfg(not(feature = "no-lookup-tables"))]
+use crate::lookup_tables::{DECIMAL_GROW_MULTIPLIERS_F64, DECIMAL_SHRINK_MULTIPLIERS_F64};
 
 use super::lookup_tables::MAX_DECIMALS;
+use crate::FloatLevel;
+
+/// Integer type used to index price levels, `u32` by default with `u64` for
+/// instruments whose tick range doesn't fit
+pub trait Tick:
+    Copy
+    + Ord
+    + Hash
+    + Default
+    + Debug
+    + Display
+    + From<u32>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Send
+    + Sync
+    + 'static
+{
+    const MIN: Self;
+    const MAX: Self;
+
+    /// cache index to tick offset, indices are bounded by `u16::MAX`
+    fn from_index(i: usize) -> Self;
+    /// tick offset to cache index, saturating at `usize::MAX`
+    fn to_index(self) -> usize;
+    fn to_f64(self) -> f64;
+    /// saturating cast, NaN maps to 0
+    fn from_f64(value: f64) -> Self;
+    fn to_u64(self) -> u64;
+    /// `None` when `value` doesn't fit
+    fn try_from_u64(value: u64) -> Option<Self>;
+
+    fn saturating_add(self, rhs: Self) -> Self;
+    fn saturating_sub(self, rhs: Self) -> Self;
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn abs_diff(self, other: Self) -> Self;
+}
+
+macro_rules! impl_tick {
+    ($t:ty) => {
+        impl Tick for $t {
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+
+            #[inline(always)]
+            fn from_index(i: usize) -> Self {
+                i as $t
+            }
+            #[inline(always)]
+            fn to_index(self) -> usize {
+                usize::try_from(self).unwrap_or(usize::MAX)
+            }
+            #[inline(always)]
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+            #[inline(always)]
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+            #[inline(always)]
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+            #[inline(always)]
+            fn try_from_u64(value: u64) -> Option<Self> {
+                <$t>::try_from(value).ok()
+            }
+
+            #[inline(always)]
+            fn saturating_add(self, rhs: Self) -> Self {
+                <$t>::saturating_add(self, rhs)
+            }
+            #[inline(always)]
+            fn saturating_sub(self, rhs: Self) -> Self {
+                <$t>::saturating_sub(self, rhs)
+            }
+            #[inline(always)]
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+            #[inline(always)]
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+            #[inline(always)]
+            fn abs_diff(self, other: Self) -> Self {
+                <$t>::abs_diff(self, other)
+            }
+        }
+    };
+}
+
+impl_tick!(u32);
+impl_tick!(u64);
 
 /// Error when creating Decimals from out-of-range values
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,10 +122,36 @@ impl Display for DecimalRangeError {
     }
 }
 
-/// Represents a decimal places value constrained to 0-18
+/// Error when converting a price to a tick
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickConversionError {
+    /// price is NaN or infinite
+    NotFinite,
+    /// price is negative or too large to be represented as a tick
+    OutOfRange,
+}
+
+impl Display for TickConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TickConversionError::NotFinite => f.write_str("price is not finite"),
+            TickConversionError::OutOfRange => f.write_str("price is out of tick range"),
+        }
+    }
+}
+
+/// Represents a decimal places value constrained to 0-18
+///
+/// ordered by precision, more decimals compare greater
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Decimals(u8);
 
+impl Display for Decimals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}dp", self.0)
+    }
+}
+
 impl Decimals {
     pub fn new<N: TryInto<u8>>(value: N) -> Result<Decimals, DecimalRangeError> {
         let value = value.try_into().map_err(|_| DecimalRangeError)?;
@@ -32,28 +162,156 @@ impl Decimals {
         }
     }
 
+    /// decimals of a power of ten tick size as venues publish it, `0.01` is 2.
+    /// sizes like `0.05` or `0.25` have no decimal count and are rejected, as
+    /// are sizes above 1
+    pub fn from_tick_size(tick_size: f64) -> Result<Decimals, DecimalRangeError> {
+        if !(tick_size > 0.0 && tick_size.is_finite()) {
+            return Err(DecimalRangeError);
+        }
+        let decimals = (-tick_size.log10()).round();
+        if !(0.0..=MAX_DECIMALS as f64).contains(&decimals) {
+            return Err(DecimalRangeError);
+        }
+        let decimals = Self(decimals as u8);
+        // relative check, parsed sizes can be a few ulps off the power of ten
+        if (tick_size * decimals.grow_multiplier_f64() - 1.0).abs() > 1e-9 {
+            return Err(DecimalRangeError);
+        }
+        Ok(decimals)
+    }
+
+    /// checked at compile time, see `decimals!`
+    pub const fn new_const<const N: u8>() -> Decimals {
+        const { assert!(N <= MAX_DECIMALS, "decimals must be between 0 and 18") };
+        Self(N)
+    }
+
     pub fn value(&self) -> u8 {
         self.0
     }
 
+    #[cfg(not(feature = "no-lookup-tables"))]
     #[inline(always)]
     fn shrink_multiplier_f64(&self) -> f64 {
         // SAFETY new validates self.0 is in range
         unsafe { *DECIMAL_SHRINK_MULTIPLIERS_F64.get_unchecked(self.0 as usize) }
     }
 
+    #[cfg(not(feature = "no-lookup-tables"))]
+    #[inline(always)]
+    pub(crate) fn grow_multiplier_f64(&self) -> f64 {
+        // SAFETY new validates self.0 is in range
+        unsafe { *DECIMAL_GROW_MULTIPLIERS_F64.get_unchecked(self.0 as usize) }
+    }
+
+    /// same values as the tables, see `computed_multipliers_match_tables`
+    #[cfg(feature = "no-lookup-tables")]
+    #[inline(always)]
+    fn shrink_multiplier_f64(&self) -> f64 {
+        10.0f64.powi(-(self.0 as i32))
+    }
+
+    #[cfg(feature = "no-lookup-tables")]
+    #[inline(always)]
+    pub(crate) fn grow_multiplier_f64(&self) -> f64 {
+        10.0f64.powi(self.0 as i32)
+    }
+
     #[inline]
-    pub fn reference_tick_to_f64(&self, tick: u32) -> f64 {
-        let f = tick as f64;
+    pub fn reference_tick_to_f64<T: Tick>(&self, tick: T) -> f64 {
+        let f = tick.to_f64();
         f * 10.0f64.powi(-(self.0 as i32))
     }
 
+    /// at 0 decimals the multiplier is 1.0 and the price is exactly the tick,
+    /// every `u32` fits the 53 bit mantissa. `u64` ticks above 2^53 lose
+    /// precision in the conversion
+    #[inline]
+    pub fn fast_tick_to_f64<T: Tick>(&self, tick: T) -> f64 {
+        tick.to_f64() * self.shrink_multiplier_f64()
+    }
+
+    /// same as `fast_tick_to_f64`
+    #[inline]
+    pub fn tick_to_f64<T: Tick>(&self, tick: T) -> f64 {
+        self.fast_tick_to_f64(tick)
+    }
+
+    /// exact fixed point price, `value = tick / 10^decimals`
+    #[inline]
+    pub fn tick_to_scaled<T: Tick>(&self, tick: T) -> (u64, u8) {
+        (tick.to_u64(), self.0)
+    }
+
+    /// exact decimal string of the price, no float rounding. keeps trailing
+    /// zeros, tick 100 at 2dp is `"1.00"`
+    pub fn format_price<T: Tick>(&self, tick: T) -> String {
+        let decimals = self.0 as usize;
+        let digits = tick.to_string();
+        if decimals == 0 {
+            return digits;
+        }
+
+        // at least one digit before the point
+        let digits = format!("{digits:0>width$}", width = decimals + 1);
+        let (int, frac) = digits.split_at(digits.len() - decimals);
+        format!("{int}.{frac}")
+    }
+
+    /// `"{size} @ {price}"` with the price at exactly this many decimals,
+    /// trailing zeros kept. hides float noise, `0.0200000001` at 2dp prints
+    /// `"0.02"`
+    pub fn format_level(&self, level: &FloatLevel) -> String {
+        format!("{} @ {:.*}", level.size, self.0 as usize, level.price)
+    }
+
+    /// rounds to the nearest tick, out of range past `T::MAX`
+    #[inline]
+    pub fn f64_to_tick<T: Tick>(&self, price: f64) -> Result<T, TickConversionError> {
+        if !price.is_finite() {
+            return Err(TickConversionError::NotFinite);
+        }
+
+        // `T::MAX + 1` is a power of two, exact as a float where `u64::MAX`
+        // itself rounds up to it
+        let tick = (price * self.grow_multiplier_f64()).round();
+        if tick < 0.0 || tick >= T::MAX.to_f64() + 1.0 {
+            return Err(TickConversionError::OutOfRange);
+        }
+
+        Ok(T::from_f64(tick))
+    }
+
+    /// rounds to the nearest tick, clamping negatives to 0 and overflow to
+    /// `T::MAX` instead of failing. NaN clamps to 0, like `Tick::from_f64`.
+    /// for lossy display pipelines, feed the book through `f64_to_tick`
     #[inline]
-    pub fn fast_tick_to_f64(&self, tick: u32) -> f64 {
-        (tick as f64) * self.shrink_multiplier_f64()
+    pub fn f64_to_tick_saturating<T: Tick>(&self, price: f64) -> T {
+        T::from_f64((price * self.grow_multiplier_f64()).round())
     }
 }
 
+/// `Decimals` checked at compile time
+///
+/// ```
+/// use orderbook::{OrderBook, decimals};
+///
+/// let book: OrderBook<4, 1> = OrderBook::new(decimals!(2));
+/// ```
+///
+/// out of range values don't compile
+///
+/// ```compile_fail,E0080
+/// let decimals = orderbook::decimals!(19);
+/// ```
+#[macro_export]
+macro_rules! decimals {
+    ($n:expr) => {
+        $crate::tick::Decimals::new_const::<$n>()
+    };
+}
+
 impl TryFrom<u8> for Decimals {
     type Error = DecimalRangeError;
 
@@ -89,6 +347,7 @@ impl TryFrom<u64> for Decimals {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lookup_tables::{DECIMAL_GROW_MULTIPLIERS_F64, DECIMAL_SHRINK_MULTIPLIERS_F64};
 
     #[test]
     fn test_tick_to_f64() {
@@ -113,4 +372,228 @@ mod tests {
             assert_eq!(reference, fast);
         }
     }
+
+    #[test]
+    fn computed_multipliers_match_tables() {
+        // what `no-lookup-tables` computes, checked whichever way this is built
+        for decimals in 0..=MAX_DECIMALS {
+            let i = decimals as usize;
+            assert_eq!(
+                10.0f64.powi(-(decimals as i32)),
+                DECIMAL_SHRINK_MULTIPLIERS_F64[i]
+            );
+            assert_eq!(
+                10.0f64.powi(decimals as i32),
+                DECIMAL_GROW_MULTIPLIERS_F64[i]
+            );
+
+            let decimals = Decimals::new(decimals).unwrap();
+            for tick in [0, 1, 7, 123, 99_999, u32::MAX] {
+                assert_eq!(
+                    decimals.fast_tick_to_f64(tick),
+                    decimals.reference_tick_to_f64(tick)
+                );
+                let price = decimals.fast_tick_to_f64(tick);
+                assert_eq!(decimals.f64_to_tick(price), Ok(tick));
+            }
+        }
+    }
+
+    #[test]
+    fn zero_decimals_exact() {
+        let decimals = Decimals::new(0u8).unwrap();
+        let powers = (0..32).flat_map(|shift| {
+            let p = 1u32 << shift;
+            [p - 1, p, p + 1]
+        });
+
+        for tick in [0, 1, u32::MAX - 1, u32::MAX].into_iter().chain(powers) {
+            assert_eq!(decimals.fast_tick_to_f64(tick), tick as f64);
+            assert_eq!(decimals.tick_to_f64(tick), tick as f64);
+            assert_eq!(decimals.f64_to_tick(tick as f64), Ok(tick));
+        }
+        assert_eq!(decimals.fast_tick_to_f64(u32::MAX), 4_294_967_295.0);
+    }
+
+    #[test]
+    fn f64_to_tick() {
+        let decimals = Decimals::new(2u8).unwrap();
+
+        assert_eq!(decimals.f64_to_tick::<u32>(1.23), Ok(123));
+        assert_eq!(decimals.f64_to_tick::<u32>(0.0), Ok(0));
+        assert_eq!(decimals.f64_to_tick::<u32>(0.1 + 0.2), Ok(30));
+        assert_eq!(
+            decimals.f64_to_tick::<u32>(-0.01),
+            Err(TickConversionError::OutOfRange)
+        );
+        assert_eq!(
+            decimals.f64_to_tick::<u32>(1e10),
+            Err(TickConversionError::OutOfRange)
+        );
+        assert_eq!(
+            decimals.f64_to_tick::<u32>(f64::NAN),
+            Err(TickConversionError::NotFinite)
+        );
+    }
+
+    #[test]
+    fn u64_ticks_past_u32() {
+        let decimals = Decimals::new(2u8).unwrap();
+        let tick = u32::MAX as u64 * 10;
+        let price = decimals.tick_to_f64(tick);
+
+        assert_eq!(decimals.f64_to_tick::<u64>(price), Ok(tick));
+        assert_eq!(
+            decimals.f64_to_tick::<u32>(price),
+            Err(TickConversionError::OutOfRange)
+        );
+        assert_eq!(decimals.f64_to_tick_saturating::<u64>(price), tick);
+        assert_eq!(decimals.f64_to_tick_saturating::<u32>(price), u32::MAX);
+        assert_eq!(decimals.format_price(tick), "429496729.50");
+        assert_eq!(decimals.tick_to_scaled(tick), (tick, 2));
+
+        // checked against `u64::MAX`, which rounds up to 2^64 as a float
+        let d0 = Decimals::new(0u8).unwrap();
+        assert_eq!(d0.f64_to_tick::<u64>(2f64.powi(63)), Ok(1 << 63));
+        assert_eq!(
+            d0.f64_to_tick::<u64>(2f64.powi(64)),
+            Err(TickConversionError::OutOfRange)
+        );
+        assert_eq!(d0.f64_to_tick_saturating::<u64>(1e30), u64::MAX);
+    }
+
+    #[test]
+    fn tick_to_f64_widths() {
+        let decimals = Decimals::new(2u8).unwrap();
+
+        assert_eq!(
+            decimals.tick_to_f64(12345u32),
+            decimals.fast_tick_to_f64(12345u32)
+        );
+        assert_eq!(
+            decimals.tick_to_f64(12345u64),
+            decimals.fast_tick_to_f64(12345u32)
+        );
+        assert_eq!(
+            decimals.tick_to_f64(u32::MAX as u64 + 1),
+            (u32::MAX as f64 + 1.0) * 0.01
+        );
+    }
+
+    #[test]
+    fn decimals_display_and_ordering() {
+        let d0 = Decimals::new(0u8).unwrap();
+        let d2 = Decimals::new(2u8).unwrap();
+        let d18 = Decimals::new(18u8).unwrap();
+
+        assert_eq!(d2.to_string(), "2dp");
+        assert_eq!(format!("{d0} < {d18}"), "0dp < 18dp");
+
+        assert!(d0 < d2 && d2 < d18);
+        assert_eq!(d2.max(d18), d18);
+
+        let mut all: Vec<_> = (0..=MAX_DECIMALS)
+            .rev()
+            .map(|v| Decimals::new(v).unwrap())
+            .collect();
+        all.sort();
+        assert!(all.iter().map(Decimals::value).eq(0..=MAX_DECIMALS));
+    }
+
+    #[test]
+    fn decimals_macro() {
+        assert_eq!(crate::decimals!(0), Decimals::new(0u8).unwrap());
+        assert_eq!(crate::decimals!(2), Decimals::new(2u8).unwrap());
+        assert_eq!(crate::decimals!(18), Decimals::new(18u8).unwrap());
+
+        const D: Decimals = crate::decimals!(4);
+        assert_eq!(D.value(), 4);
+    }
+
+    #[test]
+    fn f64_to_tick_saturating() {
+        let decimals = Decimals::new(2u8).unwrap();
+
+        assert_eq!(decimals.f64_to_tick_saturating::<u32>(1.23), 123);
+        assert_eq!(decimals.f64_to_tick_saturating::<u32>(-0.01), 0);
+        assert_eq!(decimals.f64_to_tick_saturating::<u32>(f64::NEG_INFINITY), 0);
+        assert_eq!(decimals.f64_to_tick_saturating::<u32>(1e12), u32::MAX);
+        assert_eq!(
+            decimals.f64_to_tick_saturating::<u32>(f64::INFINITY),
+            u32::MAX
+        );
+        assert_eq!(decimals.f64_to_tick_saturating::<u32>(f64::NAN), 0);
+        assert_eq!(
+            decimals.f64_to_tick_saturating::<u32>(42_949_672.95),
+            decimals.f64_to_tick::<u32>(42_949_672.95).unwrap()
+        );
+    }
+
+    #[test]
+    fn from_tick_size() {
+        assert_eq!(Decimals::from_tick_size(0.01), Decimals::new(2u8));
+        assert_eq!(Decimals::from_tick_size(0.001), Decimals::new(3u8));
+        assert_eq!(Decimals::from_tick_size(1.0), Decimals::new(0u8));
+        assert_eq!(Decimals::from_tick_size(1e-18), Decimals::new(18u8));
+        for decimals in 0..=MAX_DECIMALS {
+            let tick_size = DECIMAL_SHRINK_MULTIPLIERS_F64[decimals as usize];
+            assert_eq!(Decimals::from_tick_size(tick_size), Decimals::new(decimals));
+        }
+
+        for tick_size in [
+            0.25,
+            0.05,
+            0.02,
+            10.0,
+            1e-19,
+            0.0,
+            -0.01,
+            f64::NAN,
+            f64::INFINITY,
+        ] {
+            assert_eq!(Decimals::from_tick_size(tick_size), Err(DecimalRangeError));
+        }
+    }
+
+    #[test]
+    fn format_level() {
+        let d2 = Decimals::new(2u8).unwrap();
+        let level = |price| FloatLevel { price, size: 5.0 };
+        assert_eq!(d2.format_level(&level(0.0200000001)), "5 @ 0.02");
+        assert_eq!(d2.format_level(&level(0.1 + 0.2)), "5 @ 0.30");
+        assert_eq!(d2.format_level(&level(1.0)), "5 @ 1.00");
+        assert_eq!(
+            Decimals::new(0u8).unwrap().format_level(&level(42.0)),
+            "5 @ 42"
+        );
+    }
+
+    #[test]
+    fn format_price() {
+        let d2 = Decimals::new(2u8).unwrap();
+        assert_eq!(d2.format_price(123u32), "1.23");
+        assert_eq!(d2.format_price(5u32), "0.05");
+        assert_eq!(d2.format_price(100u32), "1.00");
+        assert_eq!(d2.format_price(0u32), "0.00");
+        assert_eq!(Decimals::new(0u8).unwrap().format_price(123u32), "123");
+        assert_eq!(d2.tick_to_scaled(123u32), (123, 2));
+
+        for decimals in 0..=MAX_DECIMALS {
+            let decimals = Decimals::new(decimals).unwrap();
+            for tick in [0u32, 1, 7, 123, 99_999, u32::MAX] {
+                let parsed: f64 = decimals.format_price(tick).parse().unwrap();
+                let float = decimals.fast_tick_to_f64(tick);
+                assert!((parsed - float).abs() <= f64::EPSILON * parsed.abs());
+            }
+        }
+
+        // exact where f64 can't be
+        let d18 = Decimals::new(18u8).unwrap();
+        assert_eq!(d18.format_price(u32::MAX), "0.000000004294967295");
+        assert_eq!(d18.format_price(1u32), "0.000000000000000001");
+        assert_eq!(
+            d18.format_price(u32::MAX).replace('.', "").parse::<u64>(),
+            Ok(u32::MAX as u64)
+        );
+    }
 }